@@ -0,0 +1,337 @@
+// ---------- call tree reconstruction ----------
+//
+// Depth logs carry no explicit enter/leave events, only a `depth` per record.
+// An invocation is reconstructed as a run of records of one function at one
+// depth, together with everything deeper logged in between:
+//
+//   depth=0 func=main          main
+//   depth=1 func=insert          insert      (one invocation: both records
+//   depth=2 func=lock              lock       plus the nested lock call)
+//   depth=1 func=insert
+//   depth=1 func=flush           flush       (different func: new sibling)
+//
+// Two back-to-back calls of the same function at the same depth with nothing
+// in between are indistinguishable from one call logging twice; they become
+// one invocation. A depth jump (0 -> 3) attaches the deeper call to the
+// nearest open shallower one; a record shallower than everything open
+// starts a new root.
+
+use std::ops::Range;
+
+use crate::record::Record;
+use crate::time::TimestampParser;
+
+/// Index of a node in [`CallTree::nodes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub usize);
+
+/// One function invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub func: Option<String>,
+    pub depth: usize,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    /// Indices of the records logged by this invocation itself.
+    pub records: Vec<usize>,
+    /// Indices of all records of the subtree; records of a subtree are
+    /// always contiguous.
+    pub span: Range<usize>,
+}
+
+/// Records plus the invocation tree over them.
+#[derive(Debug, Clone, Default)]
+pub struct CallTree {
+    pub records: Vec<Record>,
+    pub nodes: Vec<Node>,
+    pub roots: Vec<NodeId>,
+    /// For each record, the invocation it belongs to.
+    pub node_of: Vec<NodeId>,
+}
+
+impl CallTree {
+    /// Build the tree over a complete sequence of records.
+    pub fn build<I: IntoIterator<Item = Record>>(records: I) -> Self {
+        let mut b = CallTreeBuilder::new();
+        for rec in records {
+            b.push(rec);
+        }
+        b.finish()
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    /// Every record of the subtree of `id`, in input order.
+    pub fn subtree_records(&self, id: NodeId) -> &[Record] {
+        &self.records[self.node(id).span.clone()]
+    }
+
+    /// Timestamp of the first record of the invocation (its entry).
+    pub fn first_ts(&self, id: NodeId) -> Option<&str> {
+        self.subtree_records(id).first()?.ts.as_deref()
+    }
+
+    /// Timestamp of the last record anywhere in the subtree.
+    pub fn last_ts(&self, id: NodeId) -> Option<&str> {
+        self.subtree_records(id)
+            .iter()
+            .rev()
+            .find_map(|r| r.ts.as_deref())
+    }
+
+    /// Elapsed time of the invocation in nanoseconds, from its entry to the
+    /// last record of its subtree, with the records' timestamps read by
+    /// `timestamps`.
+    pub fn inclusive(&self, id: NodeId, timestamps: &TimestampParser) -> Option<i128> {
+        let first = timestamps.parse(self.first_ts(id)?)?;
+        let last = timestamps.parse(self.last_ts(id)?)?;
+        Some(last.since(&first))
+    }
+
+    /// [`CallTree::inclusive`] minus that of the direct children.
+    pub fn exclusive(&self, id: NodeId, timestamps: &TimestampParser) -> Option<i128> {
+        let children: i128 = self
+            .node(id)
+            .children
+            .iter()
+            .filter_map(|&c| self.inclusive(c, timestamps))
+            .sum();
+        Some(self.inclusive(id, timestamps)? - children)
+    }
+
+    /// Nodes in pre-order (parents before children, siblings in order).
+    pub fn preorder(&self) -> Vec<NodeId> {
+        let mut out = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            out.push(id);
+            stack.extend(self.node(id).children.iter().rev().copied());
+        }
+        out
+    }
+
+    /// The chain of invocations from a root down to `id`, inclusive.
+    pub fn ancestry(&self, id: NodeId) -> Vec<NodeId> {
+        let mut chain = vec![id];
+        let mut cur = id;
+        while let Some(p) = self.node(cur).parent {
+            chain.push(p);
+            cur = p;
+        }
+        chain.reverse();
+        chain
+    }
+}
+
+/// Whether `rec` ends the open invocation of `func` at `depth`: it is
+/// shallower, or at the same depth but from another function.
+pub(crate) fn closes(depth: usize, func: Option<&str>, rec: &Record) -> bool {
+    depth > rec.depth || (depth == rec.depth && func != rec.func.as_deref())
+}
+
+/// Incremental [`CallTree`] construction, one record at a time.
+#[derive(Debug, Default)]
+pub struct CallTreeBuilder {
+    tree: CallTree,
+    /// Open invocations, shallowest first.
+    open: Vec<NodeId>,
+}
+
+impl CallTreeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the next record; returns the invocation it was assigned to.
+    pub fn push(&mut self, rec: Record) -> NodeId {
+        let idx = self.tree.records.len();
+        let depth = rec.depth;
+
+        while let Some(&top) = self.open.last() {
+            let n = &self.tree.nodes[top.0];
+            if !closes(n.depth, n.func.as_deref(), &rec) {
+                break;
+            }
+            self.open.pop();
+        }
+
+        let id = match self.open.last().copied() {
+            Some(top) if self.tree.nodes[top.0].depth == depth => top,
+            parent => {
+                let id = NodeId(self.tree.nodes.len());
+                self.tree.nodes.push(Node {
+                    func: rec.func.clone(),
+                    depth,
+                    parent,
+                    children: Vec::new(),
+                    records: Vec::new(),
+                    span: idx..idx,
+                });
+                match parent {
+                    Some(p) => self.tree.nodes[p.0].children.push(id),
+                    None => self.tree.roots.push(id),
+                }
+                self.open.push(id);
+                id
+            }
+        };
+
+        self.tree.nodes[id.0].records.push(idx);
+        for &open in &self.open {
+            self.tree.nodes[open.0].span.end = idx + 1;
+        }
+        self.tree.node_of.push(id);
+        self.tree.records.push(rec);
+        id
+    }
+
+    /// The invocations currently open, shallowest first: the call stack as
+    /// of the last record.
+    pub fn stack(&self) -> &[NodeId] {
+        &self.open
+    }
+
+    /// The tree so far.
+    pub fn tree(&self) -> &CallTree {
+        &self.tree
+    }
+
+    pub fn finish(self) -> CallTree {
+        self.tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a tree from `depth func` pairs.
+    fn build(calls: &[(usize, &str)]) -> CallTree {
+        CallTree::build(
+            calls
+                .iter()
+                .map(|(depth, func)| Record::parse(&format!("depth={depth} func={func}")).unwrap()),
+        )
+    }
+
+    /// Each node as `func depth (own records) [children]`, in pre-order.
+    fn shape(tree: &CallTree) -> Vec<String> {
+        tree.preorder()
+            .into_iter()
+            .map(|id| {
+                let n = tree.node(id);
+                let children: Vec<usize> = n.children.iter().map(|c| c.0).collect();
+                format!(
+                    "{} {} {:?} {:?}",
+                    n.func.as_deref().unwrap_or("?"),
+                    n.depth,
+                    n.records,
+                    children
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn runs_of_one_function_are_one_invocation() {
+        let tree = build(&[
+            (0, "main"),
+            (1, "insert"),
+            (2, "lock"),
+            (1, "insert"),
+            (1, "flush"),
+        ]);
+        assert_eq!(
+            shape(&tree),
+            [
+                "main 0 [0] [1, 3]",
+                "insert 1 [1, 3] [2]",
+                "lock 2 [2] []",
+                "flush 1 [4] []",
+            ]
+        );
+        assert_eq!(tree.node(NodeId(1)).span, 1..4);
+        assert_eq!(tree.node(NodeId(0)).span, 0..5);
+        assert_eq!(
+            tree.node_of,
+            [NodeId(0), NodeId(1), NodeId(2), NodeId(1), NodeId(3)]
+        );
+    }
+
+    #[test]
+    fn back_to_back_calls_of_one_function_merge() {
+        let tree = build(&[(0, "main"), (1, "f"), (1, "f"), (1, "g"), (1, "f")]);
+        assert_eq!(
+            shape(&tree),
+            [
+                "main 0 [0] [1, 2, 3]",
+                "f 1 [1, 2] []",
+                "g 1 [3] []",
+                "f 1 [4] []",
+            ]
+        );
+    }
+
+    #[test]
+    fn depth_jumps_attach_to_the_nearest_open_call() {
+        let tree = build(&[(0, "main"), (3, "deep"), (1, "mid"), (2, "leaf")]);
+        assert_eq!(
+            shape(&tree),
+            [
+                "main 0 [0] [1, 2]",
+                "deep 3 [1] []",
+                "mid 1 [2] [3]",
+                "leaf 2 [3] []",
+            ]
+        );
+        assert_eq!(tree.ancestry(NodeId(3)), [NodeId(0), NodeId(2), NodeId(3)]);
+    }
+
+    #[test]
+    fn gaps_in_depth_do_not_reopen_closed_calls() {
+        // Back from 3 to 2 with no call open at 2: a new sibling of `deep`
+        // under `main`, not a continuation of anything.
+        let tree = build(&[(0, "main"), (1, "a"), (3, "deep"), (2, "b"), (1, "a")]);
+        assert_eq!(
+            shape(&tree),
+            [
+                "main 0 [0] [1]",
+                "a 1 [1, 4] [2, 3]",
+                "deep 3 [2] []",
+                "b 2 [3] []",
+            ]
+        );
+    }
+
+    #[test]
+    fn shallower_records_start_new_roots() {
+        let tree = build(&[(2, "late"), (3, "inner"), (1, "up"), (0, "main")]);
+        assert_eq!(tree.roots, [NodeId(0), NodeId(2), NodeId(3)]);
+        assert_eq!(tree.node(NodeId(1)).parent, Some(NodeId(0)));
+        assert_eq!(tree.node(NodeId(2)).parent, None);
+    }
+
+    #[test]
+    fn durations_from_first_and_last_timestamps() {
+        let lines = [
+            "ts=2025-01-01T00:00:00.000Z depth=0 func=main",
+            "ts=2025-01-01T00:00:00.010Z depth=1 func=f",
+            "depth=2 func=g",
+            "ts=2025-01-01T00:00:00.040Z depth=2 func=g",
+            "ts=2025-01-01T00:00:00.100Z depth=0 func=main",
+        ];
+        let tree = CallTree::build(lines.iter().map(|l| Record::parse(l).unwrap()));
+        let parser = TimestampParser::default();
+        let ms = |id| tree.inclusive(NodeId(id), &parser).map(|n| n / 1_000_000);
+        // `g` entered on an undated record: no entry time, no duration.
+        assert_eq!([ms(0), ms(1), ms(2)], [Some(100), Some(30), None]);
+        assert_eq!(tree.first_ts(NodeId(2)), None);
+        assert_eq!(tree.last_ts(NodeId(2)), Some("2025-01-01T00:00:00.040Z"));
+        assert_eq!(
+            tree.exclusive(NodeId(0), &parser).map(|n| n / 1_000_000),
+            Some(70)
+        );
+    }
+}
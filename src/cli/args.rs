@@ -0,0 +1,90 @@
+// ---------- minimal argument lexer ----------
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+/// A command-line usage error; reported with the usage text and exit code 2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliError(pub String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// One lexed argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arg {
+    /// `--name`, `--name=value` or `-n`; the name keeps its dashes.
+    Flag(String, Option<String>),
+    /// Anything else, including a lone `-` and everything after `--`.
+    Positional(String),
+}
+
+/// Splits `argv` into flags and positionals; flag values are pulled on
+/// demand by the subcommand that knows the flag takes one.
+#[derive(Debug)]
+pub struct Args {
+    rest: VecDeque<String>,
+    only_positional: bool,
+}
+
+impl Args {
+    pub fn new<I: IntoIterator<Item = String>>(args: I) -> Self {
+        Args {
+            rest: args.into_iter().collect(),
+            only_positional: false,
+        }
+    }
+
+    pub fn next_arg(&mut self) -> Option<Arg> {
+        let a = self.rest.pop_front()?;
+        if self.only_positional || a == "-" || !a.starts_with('-') {
+            return Some(Arg::Positional(a));
+        }
+        if a == "--" {
+            self.only_positional = true;
+            return self.next_arg();
+        }
+        if a.starts_with("--")
+            && let Some((name, value)) = a.split_once('=')
+        {
+            return Some(Arg::Flag(name.to_string(), Some(value.to_string())));
+        }
+        Some(Arg::Flag(a, None))
+    }
+
+    /// The value of `flag`: the inline `--flag=value` part, or the next
+    /// argument.
+    pub fn value(&mut self, flag: &str, inline: Option<String>) -> Result<String, CliError> {
+        if let Some(v) = inline {
+            return Ok(v);
+        }
+        self.rest
+            .pop_front()
+            .ok_or_else(|| CliError(format!("{flag} requires a value")))
+    }
+
+    /// Like [`Args::value`], parsed with `FromStr`.
+    pub fn parse<T>(&mut self, flag: &str, inline: Option<String>) -> Result<T, CliError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let v = self.value(flag, inline)?;
+        v.parse()
+            .map_err(|e| CliError(format!("invalid value `{v}` for {flag}: {e}")))
+    }
+}
+
+/// Reject a value for a flag that does not take one (`--flag=x`).
+pub fn no_value(flag: &str, inline: &Option<String>) -> Result<(), CliError> {
+    match inline {
+        Some(_) => Err(CliError(format!("{flag} does not take a value"))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,35 @@
+// `depthlog convert`: re-encode records.
+
+use super::args::Args;
+use super::{Common, Error, Unhandled, print_help, unknown_flag};
+use crate::convert::{Format, write_record};
+use crate::stream::for_each_record;
+
+const USAGE: &str = "\
+Usage: depthlog convert [OPTIONS] [FILE...]
+
+Re-encode every record.
+
+Options:
+  -t, --to FORMAT       logfmt (default), json (JSON lines) or tsv";
+
+pub fn run(args: &mut Args) -> Result<(), Error> {
+    let mut common = Common::default();
+    let mut format = Format::default();
+    while let Some(arg) = common.next(args)? {
+        match arg {
+            Unhandled::Help => return print_help(USAGE),
+            Unhandled::Flag(flag, inline) => match flag.as_str() {
+                "-t" | "--to" => format = args.parse(&flag, inline)?,
+                _ => return Err(unknown_flag(&flag)),
+            },
+        }
+    }
+
+    let input = common.open_input()?;
+    let (output, _) = common.open_output()?;
+    for_each_record(input, output, &common.read, |rec, out| {
+        write_record(out, rec, format)
+    })?;
+    Ok(())
+}
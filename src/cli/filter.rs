@@ -0,0 +1,69 @@
+// `depthlog filter`: select records, re-emit them as logfmt.
+
+use std::io::Write;
+
+use super::args::{Args, no_value};
+use super::{Common, Error, Unhandled, print_help, unknown_flag};
+use crate::record::Record;
+use crate::stream::{Entry, for_each_entry};
+
+const USAGE: &str = "\
+Usage: depthlog filter [OPTIONS] [FILE...]
+
+Print the records matching every given condition as normalized logfmt.
+With --malformed pass, lines that are not logfmt are copied unchanged.
+
+Options:
+      --func TEXT       func contains TEXT
+      --file TEXT       file contains TEXT
+      --grep TEXT       msg contains TEXT
+  -v, --invert          print the records that do NOT match";
+
+#[derive(Debug, Default)]
+struct Conditions {
+    func: Option<String>,
+    file: Option<String>,
+    grep: Option<String>,
+}
+
+impl Conditions {
+    fn matches(&self, rec: &Record) -> bool {
+        let contains = |field: &Option<String>, needle: &Option<String>| match needle {
+            None => true,
+            Some(n) => field.as_deref().is_some_and(|f| f.contains(n.as_str())),
+        };
+        contains(&rec.func, &self.func)
+            && contains(&rec.file, &self.file)
+            && contains(&rec.msg, &self.grep)
+    }
+}
+
+pub fn run(args: &mut Args) -> Result<(), Error> {
+    let mut common = Common::default();
+    let mut cond = Conditions::default();
+    let mut invert = false;
+    while let Some(arg) = common.next(args)? {
+        match arg {
+            Unhandled::Help => return print_help(USAGE),
+            Unhandled::Flag(flag, inline) => match flag.as_str() {
+                "--func" => cond.func = Some(args.value(&flag, inline)?),
+                "--file" => cond.file = Some(args.value(&flag, inline)?),
+                "--grep" => cond.grep = Some(args.value(&flag, inline)?),
+                "-v" | "--invert" => {
+                    no_value(&flag, &inline)?;
+                    invert = true;
+                }
+                _ => return Err(unknown_flag(&flag)),
+            },
+        }
+    }
+
+    let input = common.open_input()?;
+    let (output, _) = common.open_output()?;
+    for_each_entry(input, output, &common.read, |entry, out| match entry {
+        Entry::Record(rec) if cond.matches(rec) != invert => writeln!(out, "{}", rec.to_logfmt()),
+        Entry::Record(_) | Entry::Hidden(_) | Entry::Idle => Ok(()),
+        Entry::Raw(text) => writeln!(out, "{text}"),
+    })?;
+    Ok(())
+}
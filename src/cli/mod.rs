@@ -0,0 +1,273 @@
+// ---------- `depthlog` command line ----------
+//
+// `depthlog <subcommand> [options] [FILE...]`. Each subcommand lives in its
+// own module with a `USAGE` text and a `run` entry point; options shared by
+// all of them (inputs, output, color, indent) are handled by `Common`.
+
+pub mod args;
+mod convert;
+mod filter;
+mod pretty;
+mod stats;
+mod tree;
+mod view;
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use crate::color::ColorMode;
+use crate::stream::{ReadOptions, is_broken_pipe};
+use args::{Arg, Args, CliError};
+
+/// Why a subcommand stopped.
+#[derive(Debug)]
+pub enum Error {
+    /// Bad command line; printed with a usage hint, exit code 2.
+    Usage(CliError),
+    /// I/O failure while running; exit code 1 (0 for a closed pipe).
+    Io(io::Error),
+}
+
+impl From<CliError> for Error {
+    fn from(e: CliError) -> Self {
+        Error::Usage(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+type Runner = fn(&mut Args) -> Result<(), Error>;
+
+const SUBCOMMANDS: &[(&str, &str, Runner)] = &[
+    ("pretty", "indent and colorize records", pretty::run),
+    (
+        "filter",
+        "select records and re-emit them as logfmt",
+        filter::run,
+    ),
+    (
+        "stats",
+        "summarize levels, functions, files and depth",
+        stats::run,
+    ),
+    (
+        "convert",
+        "re-encode records as logfmt, JSON lines or TSV",
+        convert::run,
+    ),
+    (
+        "tree",
+        "print the call structure (function names only)",
+        tree::run,
+    ),
+    (
+        "view",
+        "browse and fold the call tree interactively",
+        view::run,
+    ),
+];
+
+const COMMON_HELP: &str = "\
+Common options:
+  -o, --output FILE     write to FILE instead of stdout
+      --color WHEN      auto (default), always or never
+      --indent N        spaces per depth level (default 4)
+      --duplicates HOW  repeated keys on a line: first, last (default), all
+                        or error (treat the line as malformed)
+      --malformed HOW   lines that are not logfmt: skip, pass (show as is),
+                        report (line number and reason on stderr) or strict
+                        (stop with an error); pass is the default for
+                        pretty and stats, skip for the others
+  -l, --level FILTER    drop records below a level: `warn`, or RUST_LOG-style
+                        directives by function (glob) and file, e.g.
+                        `info,btree_*=trace,file=heap_file.c:debug,lock_*=off`;
+                        the most specific match wins
+      --since TIME      drop records before TIME: an RFC 3339 timestamp, a
+                        date, a time of day (14:03:10, on the day of the
+                        first record), +DURATION (after the first record)
+                        or -DURATION (before now)
+      --until TIME      drop records after TIME; reading stops at the first
+                        one unless timestamps went backwards earlier
+      --ts-format FMT   how `ts` is written: auto (default), rfc3339,
+                        epoch-UNIT or mono-UNIT with UNIT s, ms, us or ns
+                        (1739612345.123456, 1739612345123); auto takes
+                        numbers in 2000..2100 as epoch times, others as
+                        monotonic seconds (with a fraction) or nanoseconds
+      --ts-anchor [VALUE=]TIME
+                        the monotonic reading VALUE (default 0) was taken
+                        at the RFC 3339 TIME; without it monotonic times
+                        show as time since the clock started
+  -h, --help            show this help
+Inputs are read in order; no FILE or `-` means stdin.";
+
+fn usage() -> String {
+    let mut s = String::from("Usage: depthlog <SUBCOMMAND> [OPTIONS] [FILE...]\n\nSubcommands:\n");
+    for (name, summary, _) in SUBCOMMANDS {
+        s.push_str(&format!("  {name:<10}{summary}\n"));
+    }
+    s.push_str("\nRun `depthlog <SUBCOMMAND> --help` for subcommand options.");
+    s
+}
+
+/// Entry point for the `depthlog` binary; `args` excludes argv[0].
+pub fn run<I: IntoIterator<Item = String>>(args: I) -> ExitCode {
+    let mut args = args.into_iter();
+    match args.next().as_deref() {
+        None => {
+            eprintln!("{}", usage());
+            ExitCode::from(2)
+        }
+        Some("-h" | "--help" | "help") => {
+            println!("{}", usage());
+            ExitCode::SUCCESS
+        }
+        Some("-V" | "--version") => {
+            println!("depthlog {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        Some(name) => run_subcommand(name, args),
+    }
+}
+
+/// Run one subcommand by name, e.g. `run_subcommand("pretty", args)` for the
+/// `depthlog_pretty` alias.
+pub fn run_subcommand<I: IntoIterator<Item = String>>(name: &str, args: I) -> ExitCode {
+    let Some((_, _, runner)) = SUBCOMMANDS.iter().find(|(n, _, _)| *n == name) else {
+        eprintln!("depthlog: unknown subcommand `{name}`\n\n{}", usage());
+        return ExitCode::from(2);
+    };
+    match runner(&mut Args::new(args)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Io(e)) if is_broken_pipe(&e) => ExitCode::SUCCESS,
+        Err(Error::Io(e)) => {
+            eprintln!("depthlog {name}: {e}");
+            ExitCode::FAILURE
+        }
+        Err(Error::Usage(e)) => {
+            eprintln!("depthlog {name}: {e}\nRun `depthlog {name} --help` for usage.");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Print a subcommand's help text followed by the common options.
+fn print_help(usage: &str) -> Result<(), Error> {
+    writeln!(io::stdout(), "{usage}\n\n{COMMON_HELP}")?;
+    Ok(())
+}
+
+/// Options every subcommand accepts.
+#[derive(Debug, Clone)]
+pub struct Common {
+    pub inputs: Vec<String>,
+    pub output: Option<String>,
+    pub color: ColorMode,
+    pub indent: usize,
+    pub read: ReadOptions,
+}
+
+impl Default for Common {
+    fn default() -> Self {
+        Common {
+            inputs: Vec::new(),
+            output: None,
+            color: ColorMode::Auto,
+            indent: 4,
+            read: ReadOptions::default(),
+        }
+    }
+}
+
+/// What [`Common::next`] saw that it did not handle itself.
+enum Unhandled {
+    Flag(String, Option<String>),
+    Help,
+}
+
+impl Common {
+    /// Pull the next argument the subcommand has to handle, consuming common
+    /// options and positional inputs along the way.
+    fn next(&mut self, args: &mut Args) -> Result<Option<Unhandled>, CliError> {
+        while let Some(arg) = args.next_arg() {
+            let (flag, inline) = match arg {
+                Arg::Positional(p) => {
+                    self.inputs.push(p);
+                    continue;
+                }
+                Arg::Flag(f, v) => (f, v),
+            };
+            match flag.as_str() {
+                "-o" | "--output" => self.output = Some(args.value(&flag, inline)?),
+                "--color" | "--colour" => self.color = args.parse(&flag, inline)?,
+                "--indent" => self.indent = args.parse(&flag, inline)?,
+                "--duplicates" => self.read.duplicates = args.parse(&flag, inline)?,
+                "--malformed" => self.read.malformed = args.parse(&flag, inline)?,
+                "-l" | "--level" => self.read.levels = args.parse(&flag, inline)?,
+                "--since" => self.read.since = Some(args.parse(&flag, inline)?),
+                "--until" => self.read.until = Some(args.parse(&flag, inline)?),
+                "--ts-format" => self.read.timestamps.format = args.parse(&flag, inline)?,
+                "--ts-anchor" => self.read.timestamps.anchor = Some(args.parse(&flag, inline)?),
+                "-h" | "--help" => return Ok(Some(Unhandled::Help)),
+                _ => return Ok(Some(Unhandled::Flag(flag, inline))),
+            }
+        }
+        Ok(None)
+    }
+
+    /// All inputs chained into one reader. Each file is followed by a
+    /// newline so a missing final newline cannot glue two records together.
+    fn open_input(&self) -> io::Result<Box<dyn Read + Send>> {
+        if self.inputs.is_empty() {
+            return Ok(Box::new(io::stdin()));
+        }
+        let mut input: Box<dyn Read + Send> = Box::new(io::empty());
+        for (_, next) in self.open_inputs()? {
+            input = Box::new(input.chain(next).chain(&b"\n"[..]));
+        }
+        Ok(input)
+    }
+
+    /// Every input on its own, with its name.
+    fn open_inputs(&self) -> io::Result<Vec<(String, Box<dyn Read + Send>)>> {
+        self.inputs
+            .iter()
+            .map(|path| {
+                let input: Box<dyn Read + Send> = if path == "-" {
+                    Box::new(io::stdin())
+                } else {
+                    Box::new(File::open(path).map_err(|e| with_path(path, e))?)
+                };
+                Ok((path.clone(), input))
+            })
+            .collect()
+    }
+
+    /// The output writer and whether colors are enabled for it.
+    fn open_output(&self) -> io::Result<(Box<dyn Write>, bool)> {
+        match self.output.as_deref() {
+            None | Some("-") => {
+                let stdout = io::stdout();
+                let color = self.color.enabled(&stdout);
+                Ok((Box::new(stdout.lock()), color))
+            }
+            Some(path) => {
+                let file = File::create(path).map_err(|e| with_path(path, e))?;
+                let color = self.color.enabled(&file);
+                Ok((Box::new(file), color))
+            }
+        }
+    }
+}
+
+fn with_path(path: &str, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{path}: {e}"))
+}
+
+fn unknown_flag(flag: &str) -> Error {
+    Error::Usage(CliError(format!("unknown option `{flag}`")))
+}
@@ -0,0 +1,252 @@
+// `depthlog pretty`: the classic depthlog_pretty output.
+
+use std::io::Read;
+
+use super::args::{Args, CliError, no_value};
+use super::{Common, Error, Unhandled, print_help, unknown_flag, with_path};
+use crate::follow::Follow;
+use crate::glob::Glob;
+use crate::guides::GuideStyle;
+use crate::lanes::{Lanes, input_labels};
+use crate::render::{RenderConfig, Renderer};
+use crate::stream::{Malformed, MergeInput, stream_lanes, stream_pretty, stream_threads};
+use crate::terminal::terminal_width;
+use crate::threads::{ThreadKey, ThreadMode, Threads};
+use crate::time::parse_duration;
+
+const USAGE: &str = "\
+Usage: depthlog pretty [OPTIONS] [FILE...]
+
+Print one line per record, indented by depth, with colorized level and
+function name:
+
+  HH:MM:SS.mmm [L] file:line | <indent>func: msg key=value...
+
+Fields other than ts, level, depth, file, line, func and msg are appended
+after the message, dimmed, in input order. Lines that are not logfmt are
+shown dimmed at the depth of the previous record.
+
+Several FILEs are merged by timestamp, each line starting with a label
+naming its file; every file keeps its own depths and calls.
+
+Options:
+      --time MODE       what the time column shows: absolute (the wall clock,
+                        default), relative (since the first record), delta
+                        (since the previous record) or call (since the
+                        entry of the record's call); elapsed times are
+                        colored by the time since the previous record
+      --gap DURATION    mark pauses of at least DURATION between records
+                        with a `⋮ 1.250s gap` line
+  -f, --follow          keep reading FILE as it grows, like `tail -F`: from
+                        its start, then whatever is appended, continuing
+                        with the new file when it is rotated (renamed or
+                        truncated)
+      --concat          read several FILEs one after another instead of
+                        merging them
+      --threads MODE    track calls per thread (see --thread-key) and show
+                        the threads interleaved, each line starting with
+                        its thread id, grouped one after another, or in
+                        columns side by side, as wide as the terminal
+                        (group and columns show once the input has ended;
+                        columns leave out the location and share one time
+                        column)
+      --width N         total width for --threads columns (default: the
+                        terminal's, else $COLUMNS, else 80)
+      --thread ID       only show the records of thread ID
+      --thread-key KEYS the fields naming a thread: tid, thread, pid:tid
+                        (values joined)...; comma-separated alternatives,
+                        the first present wins (default tid,thread); with
+                        --threads they are left out of the extra fields
+      --tz ZONE         show times in ZONE: original (as written, default),
+                        utc, local or a fixed offset like +09:00
+      --precision P     digits after the seconds: s, ms (default), us or ns
+      --date            show the date before the time
+      --day-separator   write a `── YYYY-MM-DD ──` line when the date changes
+      --no-extra        do not append extra fields
+      --extra KEYS      append only these extra fields (comma-separated)
+      --hide-extra KEYS never append these extra fields
+      --column KEYS     show these fields as aligned columns after the level
+      --guides STYLE    indentation: spaces (default), unicode (│ ├── └──)
+                        or ascii (| +-- \\--); guide colors cycle per depth
+      --durations       after each call, show how long it took (entry to its
+                        last nested record) and its own share without
+                        nested calls: `← func 12.4ms (self 3.1ms)`;
+                        records hidden by --level, --since or --until
+                        still count
+      --slow DURATION   highlight calls taking at least DURATION (250ms,
+                        1.5s, 40us...); implies --durations
+      --subtree PATTERN only show calls of functions matching PATTERN (a glob,
+                        `*` and `?`) and everything beneath them, shifted
+                        so the matched call is at depth 0
+      --breadcrumb      with --subtree, show the callers above each match
+      --prune PATTERN   show only the entry line of calls of functions
+                        matching PATTERN, then how many records they hid
+                        and how long they took; may be repeated
+      --min-depth N     hide records shallower than N
+      --max-depth N     hide records deeper than N
+      --rebase          shift depths so the shallowest shown record starts at
+                        the left margin (by --min-depth when given, else by
+                        the smallest depth seen so far)
+      --depth-cap N     indent at most N levels; deeper records show their
+                        depth as a `[d=37]` marker instead
+      --multiline HOW   messages with newlines: indent (continuation lines
+                        line up under the message, default) or collapse
+                        (one line, newlines shown as ⏎)";
+
+pub fn run(args: &mut Args) -> Result<(), Error> {
+    let mut common = Common::default();
+    common.read.malformed = Malformed::Pass;
+    let mut config = RenderConfig::default();
+    let mut concat = false;
+    let mut follow = false;
+    let mut threads = None;
+    let mut thread_key = ThreadKey::default();
+    let mut width = None;
+    while let Some(arg) = common.next(args)? {
+        match arg {
+            Unhandled::Help => return print_help(USAGE),
+            Unhandled::Flag(flag, inline) => match flag.as_str() {
+                "--time" => config.time_format.mode = args.parse(&flag, inline)?,
+                "--gap" => {
+                    let v = args.value(&flag, inline)?;
+                    config.gap_threshold = Some(parse_duration(&v).map_err(CliError)?);
+                }
+                "-f" | "--follow" => {
+                    no_value(&flag, &inline)?;
+                    follow = true;
+                }
+                "--concat" => {
+                    no_value(&flag, &inline)?;
+                    concat = true;
+                }
+                "--threads" => threads = Some(args.parse(&flag, inline)?),
+                "--thread" => threads = Some(ThreadMode::Only(args.value(&flag, inline)?)),
+                "--width" => width = Some(args.parse(&flag, inline)?),
+                "--thread-key" => thread_key = args.parse(&flag, inline)?,
+                "--tz" => config.time_format.zone = args.parse(&flag, inline)?,
+                "--precision" => config.time_format.precision = args.parse(&flag, inline)?,
+                "--date" => {
+                    no_value(&flag, &inline)?;
+                    config.time_format.date = true;
+                }
+                "--day-separator" => {
+                    no_value(&flag, &inline)?;
+                    config.day_separator = true;
+                }
+                "--no-extra" => {
+                    no_value(&flag, &inline)?;
+                    config.show_extra = false;
+                }
+                "--extra" => config
+                    .extra_include
+                    .extend(keys(&args.value(&flag, inline)?)),
+                "--hide-extra" => config
+                    .extra_exclude
+                    .extend(keys(&args.value(&flag, inline)?)),
+                "--guides" => config.guides = args.parse(&flag, inline)?,
+                "--durations" => {
+                    no_value(&flag, &inline)?;
+                    config.durations = true;
+                }
+                "--slow" => {
+                    let v = args.value(&flag, inline)?;
+                    let min = parse_duration(&v).map_err(CliError)?;
+                    config.slow_threshold = Some(min);
+                    config.durations = true;
+                }
+                "--subtree" => config.subtree = Some(Glob::new(&args.value(&flag, inline)?)),
+                "--prune" => config.prune.push(Glob::new(&args.value(&flag, inline)?)),
+                "--min-depth" => config.min_depth = Some(args.parse(&flag, inline)?),
+                "--max-depth" => config.max_depth = Some(args.parse(&flag, inline)?),
+                "--rebase" => {
+                    no_value(&flag, &inline)?;
+                    config.rebase = true;
+                }
+                "--depth-cap" => config.depth_cap = Some(args.parse(&flag, inline)?),
+                "--breadcrumb" => {
+                    no_value(&flag, &inline)?;
+                    config.breadcrumb = true;
+                }
+                "--multiline" => config.multiline = args.parse(&flag, inline)?,
+                "--column" => config.columns.extend(keys(&args.value(&flag, inline)?)),
+                _ => return Err(unknown_flag(&flag)),
+            },
+        }
+    }
+
+    if config.breadcrumb && config.subtree.is_none() {
+        return Err(CliError("--breadcrumb needs --subtree".into()).into());
+    }
+    if follow && (common.inputs.len() != 1 || common.inputs[0] == "-") {
+        return Err(CliError("--follow needs exactly one FILE".into()).into());
+    }
+    if follow && matches!(threads, Some(ThreadMode::Group | ThreadMode::Columns)) {
+        return Err(CliError(
+            "--threads group and columns show once the input has ended, which --follow never does"
+                .into(),
+        )
+        .into());
+    }
+    let merge = common.inputs.len() > 1 && !concat;
+    if merge && threads.is_some() {
+        return Err(CliError(
+            "--threads and --thread read a single stream; add --concat for several files".into(),
+        )
+        .into());
+    }
+    let lanes = merge || matches!(threads, Some(ThreadMode::Interleave | ThreadMode::Columns));
+    if lanes && config.guides != GuideStyle::Spaces {
+        return Err(CliError(
+            "--guides draws a single call tree; it cannot show several files or threads side by side"
+                .into(),
+        )
+        .into());
+    }
+
+    let (output, color) = common.open_output()?;
+    config.color = color;
+    config.indent_width = common.indent;
+    config.timestamps = common.read.timestamps.clone();
+    // Calls are timed by every record, shown or not.
+    common.read.hidden = config.durations;
+    if merge {
+        let mut lanes = Lanes::new(&config);
+        let labels = input_labels(&common.inputs);
+        let mut inputs = Vec::new();
+        for ((name, input), label) in common.open_inputs()?.into_iter().zip(&labels) {
+            let timestamps = common.read.timestamps.fresh();
+            lanes.add_input(label, timestamps.clone());
+            inputs.push(MergeInput {
+                name,
+                input,
+                timestamps,
+            });
+        }
+        stream_lanes(inputs, output, &common.read, &mut lanes)?;
+        return Ok(());
+    }
+    let input: Box<dyn Read + Send> = if follow {
+        let path = &common.inputs[0];
+        Box::new(Follow::open(path).map_err(|e| with_path(path, e))?)
+    } else {
+        common.open_input()?
+    };
+    if let Some(mode) = threads {
+        let mut threads = Threads::new(config, thread_key, mode);
+        let columns = std::env::var("COLUMNS").ok().and_then(|c| c.parse().ok());
+        threads.set_width(width.or_else(terminal_width).or(columns).unwrap_or(80));
+        stream_threads(input, output, &common.read, &mut threads)?;
+        return Ok(());
+    }
+    let mut renderer = Renderer::new(config);
+    stream_pretty(input, output, &common.read, &mut renderer)?;
+    Ok(())
+}
+
+/// Split a comma-separated key list, ignoring empty entries.
+fn keys(list: &str) -> impl Iterator<Item = String> + '_ {
+    list.split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,47 @@
+// `depthlog stats`: summary counters.
+
+use std::io::Write;
+
+use super::args::Args;
+use super::{Common, Error, Unhandled, print_help, unknown_flag};
+use crate::stats::Stats;
+use crate::stream::{Entry, Malformed, for_each_entry};
+
+const USAGE: &str = "\
+Usage: depthlog stats [OPTIONS] [FILE...]
+
+Count records per level, function and file, and report the maximum depth,
+the first/last timestamp and the number of lines that are not logfmt.
+
+Options:
+      --top N           list at most N functions and files (default 10)";
+
+pub fn run(args: &mut Args) -> Result<(), Error> {
+    let mut common = Common::default();
+    common.read.malformed = Malformed::Pass;
+    let mut top = 10;
+    while let Some(arg) = common.next(args)? {
+        match arg {
+            Unhandled::Help => return print_help(USAGE),
+            Unhandled::Flag(flag, inline) => match flag.as_str() {
+                "--top" => top = args.parse(&flag, inline)?,
+                _ => return Err(unknown_flag(&flag)),
+            },
+        }
+    }
+
+    let input = common.open_input()?;
+    let (mut output, _) = common.open_output()?;
+    let mut stats = Stats::new();
+    for_each_entry(input, std::io::sink(), &common.read, |entry, _| {
+        match entry {
+            Entry::Record(rec) => stats.add(rec),
+            Entry::Raw(_) => stats.malformed += 1,
+            Entry::Hidden(_) | Entry::Idle => {}
+        }
+        Ok(())
+    })?;
+    stats.write_report(&mut output, top)?;
+    output.flush()?;
+    Ok(())
+}
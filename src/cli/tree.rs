@@ -0,0 +1,54 @@
+// `depthlog tree`: function names only, one line per invocation.
+
+use std::io::Write;
+
+use super::args::Args;
+use super::{Common, Error, Unhandled, print_help, unknown_flag};
+use crate::calltree::CallTreeBuilder;
+use crate::color::color_func;
+use crate::guides::MAX_DEPTH;
+use crate::stream::for_each_record;
+
+const USAGE: &str = "\
+Usage: depthlog tree [OPTIONS] [FILE...]
+
+Print the reconstructed call tree: one indented line per function
+invocation, with the number of records it logged itself and in its whole
+subtree (own/total).";
+
+pub fn run(args: &mut Args) -> Result<(), Error> {
+    let mut common = Common::default();
+    if let Some(arg) = common.next(args)? {
+        match arg {
+            Unhandled::Help => return print_help(USAGE),
+            Unhandled::Flag(flag, _) => return Err(unknown_flag(&flag)),
+        }
+    }
+
+    let input = common.open_input()?;
+    let (mut output, color) = common.open_output()?;
+
+    let mut builder = CallTreeBuilder::new();
+    for_each_record(input, std::io::sink(), &common.read, |rec, _| {
+        builder.push(rec.clone());
+        Ok(())
+    })?;
+    let tree = builder.finish();
+
+    for id in tree.preorder() {
+        let node = tree.node(id);
+        // A bogus `depth=` must not cost one indent per level.
+        let pad = " ".repeat(node.depth.min(MAX_DEPTH) * common.indent);
+        let func = node.func.as_deref().unwrap_or("?");
+        let name = if color {
+            color_func(func)
+        } else {
+            func.to_string()
+        };
+        let own = node.records.len();
+        let total = node.span.len();
+        writeln!(output, "{pad}{name} ({own}/{total})")?;
+    }
+    output.flush()?;
+    Ok(())
+}
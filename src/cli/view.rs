@@ -0,0 +1,106 @@
+// `depthlog view`: interactive, foldable call tree viewer.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::args::{Args, CliError};
+use super::{Common, Error, Unhandled, print_help, unknown_flag};
+use crate::calltree::CallTreeBuilder;
+use crate::render::RenderConfig;
+use crate::stream::for_each_record;
+use crate::terminal::Terminal;
+use crate::viewer::Viewer;
+
+const USAGE: &str = "\
+Usage: depthlog view [OPTIONS] [FILE...]
+
+Load the whole log and browse it in the terminal: records are shown as in
+`depthlog pretty`, and every function call can be folded to its first line.
+The top line shows the call stack of the record under the cursor; the step
+keys walk through the trace like a debugger, by depth.
+Keys are read from the terminal, so the log may come from a pipe.
+
+Keys:
+  j k Down Up       next / previous line
+  space PgDn PgUp   next / previous page
+  g G Home End      first / last line
+  Enter Tab         fold / unfold the call under the cursor
+  h Left / l Right  fold (then go to the parent call) / unfold
+  - +               fold / unfold everything
+  s S               step into: next / previous record
+  o O               step over: next / previous record at the same or a
+                    lower depth
+  u U               step out: next / previous record at a lower depth
+  p                 parent call
+  ] [               next / previous sibling call
+  / n N             search func, file, msg and fields; next / previous match
+  1 2 3 4           toggle time, level, file:line, extra fields
+  ?                 help
+  q Ctrl-C          quit";
+
+/// How often the terminal size is checked while no keys arrive; asking
+/// spawns `stty`.
+const RESIZE_POLL: Duration = Duration::from_secs(1);
+
+pub fn run(args: &mut Args) -> Result<(), Error> {
+    let mut common = Common::default();
+    if let Some(arg) = common.next(args)? {
+        match arg {
+            Unhandled::Help => return print_help(USAGE),
+            Unhandled::Flag(flag, _) => return Err(unknown_flag(&flag)),
+        }
+    }
+    if common.output.is_some() {
+        return Err(
+            CliError("view draws on the terminal; --output is not supported".into()).into(),
+        );
+    }
+
+    let input = common.open_input()?;
+    let mut builder = CallTreeBuilder::new();
+    for_each_record(input, io::sink(), &common.read, |rec, _| {
+        builder.push(rec.clone());
+        Ok(())
+    })?;
+    let tree = builder.finish();
+    if tree.records.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no records to view").into());
+    }
+
+    let name = match common.inputs.as_slice() {
+        [] => "<stdin>".to_string(),
+        [one] => one.clone(),
+        [first, rest @ ..] => format!("{first} (+{})", rest.len()),
+    };
+    let mut term = Terminal::open()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot open the terminal: {e}")))?;
+    let config = RenderConfig {
+        color: common.color.enabled(term.tty()),
+        indent_width: common.indent,
+        timestamps: common.read.timestamps.clone(),
+        ..RenderConfig::default()
+    };
+    let mut viewer = Viewer::new(tree, config, &name);
+
+    let mut size = term.size();
+    let mut checked = Instant::now();
+    term.write_frame(&viewer.draw(size.0, size.1))?;
+    loop {
+        let keys = term.read_keys()?;
+        if keys.is_empty() && checked.elapsed() < RESIZE_POLL {
+            continue;
+        }
+        let new_size = term.size();
+        checked = Instant::now();
+        if keys.is_empty() && new_size == size {
+            continue;
+        }
+        size = new_size;
+        for key in keys {
+            if !viewer.handle_key(key) {
+                return Ok(());
+            }
+        }
+        term.write_frame(&viewer.draw(size.0, size.1))?;
+    }
+}
@@ -0,0 +1,284 @@
+// ---------- ANSI coloring helpers ----------
+
+use std::io::IsTerminal;
+
+pub const RESET: &str = "\x1b[0m";
+pub const BOLD: &str = "\x1b[1m";
+pub const DIM: &str = "\x1b[2m";
+
+// Standard ANSI colors
+pub const RED: &str = "\x1b[31m";
+pub const GREEN: &str = "\x1b[32m";
+pub const YELLOW: &str = "\x1b[33m";
+pub const BLUE: &str = "\x1b[34m";
+pub const MAGENTA: &str = "\x1b[35m";
+pub const CYAN: &str = "\x1b[36m";
+pub const WHITE: &str = "\x1b[37m";
+
+/// The `--color` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Colors when the stream is a terminal, subject to `NO_COLOR` /
+    /// `FORCE_COLOR`.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn enabled<T: IsTerminal>(self, stream: &T) -> bool {
+        match self {
+            ColorMode::Auto => should_use_color(stream),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+impl std::str::FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            other => Err(format!("expected auto, always or never, got `{other}`")),
+        }
+    }
+}
+
+/// Decide whether to emit ANSI colors for the given output stream.
+///
+/// `NO_COLOR` (https://no-color.org/) always wins; a non-empty `FORCE_COLOR`
+/// other than `0` forces colors on; otherwise colors follow `is_terminal()`.
+pub fn should_use_color<T: IsTerminal>(stream: &T) -> bool {
+    if std::env::var_os("NO_COLOR").is_some() {
+        return false;
+    }
+    if let Ok(v) = std::env::var("FORCE_COLOR")
+        && v != "0"
+        && !v.is_empty()
+    {
+        return true;
+    }
+    stream.is_terminal()
+}
+
+/// Color `text` by how long `nanos` is: dim under 1ms, plain under 10ms,
+/// then yellow, red from 100ms and bold red from 1s.
+pub fn color_heat(nanos: i128, text: &str) -> String {
+    if nanos < 1_000_000 {
+        format!("{DIM}{text}{RESET}")
+    } else if nanos < 10_000_000 {
+        text.to_string()
+    } else if nanos < 100_000_000 {
+        format!("{YELLOW}{text}{RESET}")
+    } else if nanos < 1_000_000_000 {
+        format!("{RED}{text}{RESET}")
+    } else {
+        format!("{BOLD}{RED}{text}{RESET}")
+    }
+}
+
+/// Colorize a one-letter level as produced by [`crate::level::map_level`].
+pub fn color_level(ch: char) -> String {
+    match ch {
+        'E' => format!("{BOLD}{RED}{ch}{RESET}"),
+        'W' => format!("{BOLD}{YELLOW}{ch}{RESET}"),
+        'I' => format!("{BOLD}{GREEN}{ch}{RESET}"),
+        'D' => format!("{BOLD}{BLUE}{ch}{RESET}"),
+        'T' => format!("{BOLD}{MAGENTA}{ch}{RESET}"),
+        _ => format!("{BOLD}{WHITE}{ch}{RESET}"),
+    }
+}
+
+pub fn color_func(func: &str) -> String {
+    // Function name: bold cyan (adjust if desired)
+    format!("{BOLD}{CYAN}{func}{RESET}")
+}
+
+/// Display width of `s` in columns, not counting ANSI escape sequences:
+/// wide chars count two, combining marks none.
+pub fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            skip_escape(&mut chars);
+        } else {
+            width += char_width(c);
+        }
+    }
+    width
+}
+
+/// Cut `s` to at most `width` visible columns, keeping every escape
+/// sequence so colors stay balanced. A wide char that does not fit is left
+/// out whole, with the marks combining with it.
+pub fn truncate_visible(s: &str, width: usize) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut used = 0;
+    let mut cut = false;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            out.push(c);
+            let rest = chars.as_str();
+            skip_escape(&mut chars);
+            out.push_str(&rest[..rest.len() - chars.as_str().len()]);
+        } else if !cut && used + char_width(c) <= width {
+            out.push(c);
+            used += char_width(c);
+        } else {
+            cut = true;
+        }
+    }
+    out
+}
+
+/// Columns a terminal gives `c`: 2 for East Asian wide and fullwidth chars
+/// (CJK, Hangul, kana) and emoji, 0 for combining marks and zero-width
+/// chars, 1 otherwise.
+fn char_width(c: char) -> usize {
+    match c {
+        '\u{0300}'..='\u{036f}'
+        | '\u{0483}'..='\u{0489}'
+        | '\u{0591}'..='\u{05bd}'
+        | '\u{0610}'..='\u{061a}'
+        | '\u{064b}'..='\u{065f}'
+        | '\u{0e31}'
+        | '\u{0e34}'..='\u{0e3a}'
+        | '\u{0e47}'..='\u{0e4e}'
+        | '\u{1ab0}'..='\u{1aff}'
+        | '\u{1dc0}'..='\u{1dff}'
+        | '\u{200b}'..='\u{200f}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{20d0}'..='\u{20ff}'
+        | '\u{302a}'..='\u{302f}'
+        | '\u{3099}'..='\u{309a}'
+        | '\u{fe00}'..='\u{fe0f}'
+        | '\u{fe20}'..='\u{fe2f}'
+        | '\u{feff}'
+        | '\u{e0100}'..='\u{e01ef}' => 0,
+        '\u{1100}'..='\u{115f}'
+        | '\u{231a}'..='\u{231b}'
+        | '\u{2329}'..='\u{232a}'
+        | '\u{23e9}'..='\u{23ec}'
+        | '\u{23f0}'
+        | '\u{23f3}'
+        | '\u{25fd}'..='\u{25fe}'
+        | '\u{2614}'..='\u{2615}'
+        | '\u{2648}'..='\u{2653}'
+        | '\u{267f}'
+        | '\u{2693}'
+        | '\u{26a1}'
+        | '\u{26aa}'..='\u{26ab}'
+        | '\u{26bd}'..='\u{26be}'
+        | '\u{26c4}'..='\u{26c5}'
+        | '\u{26ce}'
+        | '\u{26d4}'
+        | '\u{26ea}'
+        | '\u{26f2}'..='\u{26f3}'
+        | '\u{26f5}'
+        | '\u{26fa}'
+        | '\u{26fd}'
+        | '\u{2705}'
+        | '\u{270a}'..='\u{270b}'
+        | '\u{2728}'
+        | '\u{274c}'
+        | '\u{274e}'
+        | '\u{2753}'..='\u{2755}'
+        | '\u{2757}'
+        | '\u{2795}'..='\u{2797}'
+        | '\u{27b0}'
+        | '\u{27bf}'
+        | '\u{2b1b}'..='\u{2b1c}'
+        | '\u{2b50}'
+        | '\u{2b55}'
+        | '\u{2e80}'..='\u{3029}'
+        | '\u{3030}'..='\u{303e}'
+        | '\u{3041}'..='\u{3098}'
+        | '\u{309b}'..='\u{33ff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{a4cf}'
+        | '\u{a960}'..='\u{a97f}'
+        | '\u{ac00}'..='\u{d7a3}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{fe10}'..='\u{fe19}'
+        | '\u{fe30}'..='\u{fe6f}'
+        | '\u{ff00}'..='\u{ff60}'
+        | '\u{ffe0}'..='\u{ffe6}'
+        | '\u{1f004}'
+        | '\u{1f0cf}'
+        | '\u{1f18e}'
+        | '\u{1f191}'..='\u{1f19a}'
+        | '\u{1f200}'..='\u{1f251}'
+        | '\u{1f300}'..='\u{1f64f}'
+        | '\u{1f680}'..='\u{1f6ff}'
+        | '\u{1f7e0}'..='\u{1f7eb}'
+        | '\u{1f900}'..='\u{1f9ff}'
+        | '\u{1fa70}'..='\u{1faff}'
+        | '\u{20000}'..='\u{2fffd}'
+        | '\u{30000}'..='\u{3fffd}' => 2,
+        _ => 1,
+    }
+}
+
+/// Skip the rest of a CSI sequence (`ESC [ ... final`) after its `ESC`.
+fn skip_escape(chars: &mut std::str::Chars<'_>) {
+    if chars.clone().next() != Some('[') {
+        return;
+    }
+    chars.next();
+    for c in chars.by_ref() {
+        if ('@'..='~').contains(&c) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_take_no_columns() {
+        assert_eq!(visible_width(&format!("{BOLD}{RED}E{RESET} f")), 3);
+        assert_eq!(visible_width("\x1b[38;5;208mx\x1b[0m"), 1);
+    }
+
+    #[test]
+    fn wide_and_combining_chars() {
+        // CJK, Hangul, fullwidth Latin and emoji take two columns.
+        assert_eq!(visible_width("\u{65e5}\u{672c}"), 4);
+        assert_eq!(visible_width("\u{d55c}\u{ae00}"), 4);
+        assert_eq!(visible_width("\u{ff21}b"), 3);
+        assert_eq!(visible_width("\u{1f600}!"), 3);
+        // e + combining acute, a zero-width space, a variation selector.
+        assert_eq!(visible_width("e\u{301}"), 1);
+        assert_eq!(visible_width("a\u{200b}b\u{fe0f}"), 2);
+        assert_eq!(visible_width("caf\u{e9} \u{2502}"), 6);
+    }
+
+    #[test]
+    fn truncation_counts_columns() {
+        assert_eq!(truncate_visible("abcdef", 3), "abc");
+        assert_eq!(
+            truncate_visible("\u{65e5}\u{672c}\u{8a9e}", 4),
+            "\u{65e5}\u{672c}"
+        );
+        // A wide char that would straddle the edge is dropped whole.
+        assert_eq!(truncate_visible("a\u{65e5}b", 2), "a");
+        assert_eq!(truncate_visible("e\u{301}x", 1), "e\u{301}");
+        assert_eq!(truncate_visible("a\u{65e5}\u{301}", 2), "a");
+    }
+
+    #[test]
+    fn truncation_keeps_escapes() {
+        let colored = format!("{RED}abc{RESET}d");
+        assert_eq!(truncate_visible(&colored, 2), format!("{RED}ab{RESET}"));
+        assert_eq!(truncate_visible(&colored, 0), format!("{RED}{RESET}"));
+    }
+}
@@ -0,0 +1,114 @@
+// ---------- record re-encoding ----------
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::record::Record;
+
+/// Output encodings for `depthlog convert`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Normalized logfmt, one record per line.
+    #[default]
+    Logfmt,
+    /// JSON Lines: one object per record.
+    Json,
+    /// Tab-separated `ts level depth file line func msg`, no header.
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "logfmt" => Ok(Format::Logfmt),
+            "json" | "jsonl" => Ok(Format::Json),
+            "tsv" => Ok(Format::Tsv),
+            other => Err(format!("expected logfmt, json or tsv, got `{other}`")),
+        }
+    }
+}
+
+/// Write `rec` in `format`, including the trailing newline.
+pub fn write_record<W: Write>(w: &mut W, rec: &Record, format: Format) -> io::Result<()> {
+    let line = match format {
+        Format::Logfmt => rec.to_logfmt(),
+        Format::Json => to_json(rec),
+        Format::Tsv => to_tsv(rec),
+    };
+    writeln!(w, "{line}")
+}
+
+fn to_json(rec: &Record) -> String {
+    let mut out = String::from("{");
+    let mut first = true;
+    let mut key = |out: &mut String, k: &str| {
+        if !first {
+            out.push(',');
+        }
+        first = false;
+        push_json_str(out, k);
+        out.push(':');
+    };
+
+    for (k, v) in [("ts", &rec.ts), ("level", &rec.level)] {
+        if let Some(v) = v {
+            key(&mut out, k);
+            push_json_str(&mut out, v);
+        }
+    }
+    key(&mut out, "depth");
+    let _ = write!(out, "{}", rec.depth);
+    for (k, v) in [
+        ("file", &rec.file),
+        ("line", &rec.line),
+        ("func", &rec.func),
+        ("msg", &rec.msg),
+    ] {
+        if let Some(v) = v {
+            key(&mut out, k);
+            push_json_str(&mut out, v);
+        }
+    }
+    for (k, v) in &rec.extra {
+        key(&mut out, k);
+        push_json_str(&mut out, v);
+    }
+    out.push('}');
+    out
+}
+
+fn push_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn to_tsv(rec: &Record) -> String {
+    // Tabs and newlines inside a column would break the row; flatten them.
+    let clean = |s: Option<&str>| s.unwrap_or("").replace(['\t', '\n', '\r'], " ");
+    [
+        clean(rec.ts.as_deref()),
+        clean(rec.level.as_deref()),
+        rec.depth.to_string(),
+        clean(rec.file.as_deref()),
+        clean(rec.line.as_deref()),
+        clean(rec.func.as_deref()),
+        clean(rec.msg.as_deref()),
+    ]
+    .join("\t")
+}
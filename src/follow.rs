@@ -0,0 +1,182 @@
+// ---------- following a growing file ----------
+//
+// `tail -F` built in: a reader over a file path that never reaches the
+// end. At the end of the data it polls the path; when the file there is a
+// different one (rename-based rotation) it goes on with the new file from
+// its start, and when the file got shorter (`copytruncate`) it starts over.
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the path is checked once everything has been read.
+const POLL: Duration = Duration::from_millis(250);
+
+/// Reads a file and whatever is appended to it later, across rotations.
+/// `read` blocks instead of returning end of input.
+pub struct Follow {
+    path: PathBuf,
+    file: File,
+    /// Identity of `file`, see [`identity`].
+    id: FileId,
+    pos: u64,
+    /// Whether the last byte handed out ended a line (or nothing was read
+    /// yet), so switching files cannot glue two lines together.
+    at_line_start: bool,
+}
+
+impl Follow {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path)?;
+        let id = identity(&file.metadata()?);
+        Ok(Follow {
+            path,
+            file,
+            id,
+            pos: 0,
+            at_line_start: true,
+        })
+    }
+
+    /// At the end of the open file: move to a new file at the path, or back
+    /// to the start of a truncated one. Returns whether anything changed. A
+    /// missing path (between rename and re-creation) changes nothing.
+    fn check(&mut self) -> io::Result<bool> {
+        let Ok(meta) = fs::metadata(&self.path) else {
+            return Ok(false);
+        };
+        if identity(&meta) != self.id {
+            let Ok(file) = File::open(&self.path) else {
+                return Ok(false);
+            };
+            self.id = identity(&file.metadata()?);
+            self.file = file;
+            self.pos = 0;
+            return Ok(true);
+        }
+        if meta.len() < self.pos {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.pos = 0;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+impl Read for Follow {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                self.pos += n as u64;
+                self.at_line_start = buf[n - 1] == b'\n';
+                return Ok(n);
+            }
+            if !self.check()? {
+                thread::sleep(POLL);
+            } else if !self.at_line_start {
+                // The old file ended mid-line.
+                buf[0] = b'\n';
+                self.at_line_start = true;
+                return Ok(1);
+            }
+        }
+    }
+}
+
+/// What tells a new file at the path from the one being read: device and
+/// inode where there are such, and the creation time where the system
+/// keeps one. With neither only truncation is noticed.
+#[derive(Debug, PartialEq, Eq)]
+struct FileId {
+    inode: Option<(u64, u64)>,
+    created: Option<SystemTime>,
+}
+
+fn identity(meta: &Metadata) -> FileId {
+    // Only this differs between systems, so the rest is built everywhere.
+    #[cfg(unix)]
+    let inode = Some((meta.dev(), meta.ino()));
+    #[cfg(not(unix))]
+    let inode = None;
+    FileId {
+        inode,
+        created: meta.created().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::mpsc;
+
+    /// A file in the temporary directory, removed (with its rotated copy)
+    /// when dropped.
+    struct Scratch(PathBuf);
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+            let _ = fs::remove_file(self.0.with_extension("1"));
+        }
+    }
+
+    fn append(path: &Path, text: &str) {
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn follows_appends_truncation_and_rotation() {
+        let scratch = Scratch(
+            std::env::temp_dir().join(format!("depthlog-follow-{}.log", std::process::id())),
+        );
+        let path = scratch.0.clone();
+        fs::write(&path, "a\n").unwrap();
+        let follow = Follow::open(&path).unwrap();
+        let (tx, rx) = mpsc::channel();
+        // Never returns; it ends with the test process.
+        thread::spawn(move || {
+            for line in BufReader::new(follow).lines() {
+                if tx.send(line.unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+        let next = || rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(next(), "a");
+
+        append(&path, "b\n");
+        assert_eq!(next(), "b");
+
+        // copytruncate: shorter than what was read, so read from the start.
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(0)
+            .unwrap();
+        append(&path, "c\n");
+        assert_eq!(next(), "c");
+
+        // Renamed away mid-line, then a new file at the path.
+        append(&path, "d");
+        thread::sleep(POLL * 2);
+        fs::rename(&path, path.with_extension("1")).unwrap();
+        fs::write(&path, "e\n").unwrap();
+        assert_eq!(next(), "d");
+        assert_eq!(next(), "e");
+
+        append(&path, "f\n");
+        assert_eq!(next(), "f");
+    }
+}
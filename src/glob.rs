@@ -0,0 +1,112 @@
+// ---------- shell-style wildcards ----------
+
+/// A pattern where `*` matches any run of characters and `?` any single
+/// character; everything else matches itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+    pattern: Vec<char>,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        Glob {
+            pattern: pattern.chars().collect(),
+        }
+    }
+
+    /// Whether the pattern has no wildcards.
+    pub fn is_literal(&self) -> bool {
+        !self.pattern.iter().any(|&c| c == '*' || c == '?')
+    }
+
+    /// Number of non-wildcard characters; a rough measure of how specific
+    /// the pattern is.
+    pub fn literal_len(&self) -> usize {
+        self.pattern
+            .iter()
+            .filter(|&&c| c != '*' && c != '?')
+            .count()
+    }
+
+    /// Whether the whole of `text` matches.
+    pub fn matches(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let (mut p, mut t) = (0, 0);
+        // Position of the last `*` and the text position it was tried at.
+        let mut star: Option<(usize, usize)> = None;
+        while t < text.len() {
+            match self.pattern.get(p) {
+                Some('*') => {
+                    star = Some((p, t));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == text[t] => {
+                    p += 1;
+                    t += 1;
+                }
+                _ => match star {
+                    // Let the last `*` swallow one more character.
+                    Some((sp, st)) => {
+                        p = sp + 1;
+                        t = st + 1;
+                        star = Some((sp, st + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.pattern[p..].iter().all(|&c| c == '*')
+    }
+}
+
+impl std::fmt::Display for Glob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.pattern.iter().try_for_each(|c| write!(f, "{c}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Glob::new(pattern).matches(text)
+    }
+
+    #[test]
+    fn literal_and_wildcards() {
+        assert!(matches("btree_insert", "btree_insert"));
+        assert!(!matches("btree_insert", "btree_insert2"));
+        assert!(matches("btree_*", "btree_insert"));
+        assert!(matches("btree_*", "btree_"));
+        assert!(!matches("btree_*", "xbtree_insert"));
+        assert!(matches("*_insert", "heap_insert"));
+        assert!(matches("lock_?", "lock_a"));
+        assert!(!matches("lock_?", "lock_"));
+        assert!(matches("*", ""));
+        assert!(!matches("?", ""));
+    }
+
+    #[test]
+    fn backtracking_stars() {
+        assert!(matches("*a*b", "xaxxab"));
+        assert!(matches("a*b*c", "abbbc"));
+        assert!(!matches("a*b*c", "abcb"));
+        assert!(matches("*.c", "heap_file.c"));
+        assert!(matches("**x", "aax"));
+    }
+
+    #[test]
+    fn non_ascii_characters() {
+        assert!(matches("h?llo", "héllo"));
+        assert!(matches("*😀", "smile 😀"));
+    }
+
+    #[test]
+    fn specificity() {
+        assert!(Glob::new("main").is_literal());
+        assert!(!Glob::new("ma*").is_literal());
+        assert_eq!(Glob::new("b?ree_*").literal_len(), 5);
+        assert_eq!(Glob::new("b?ree_*").to_string(), "b?ree_*");
+    }
+}
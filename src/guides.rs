@@ -0,0 +1,319 @@
+// ---------- tree guide lines ----------
+//
+// Instead of plain `depth * indent` spaces, each depth level gets a column
+// with a vertical guide while the call at that level still has siblings to
+// come:
+//
+//   main: start
+//   ├── xbtree_insert: insert
+//   │   └── lock_object: lock
+//   └── xbtree_insert: done
+//   main: end
+//
+// Whether a record is drawn with `├` or `└` depends on records that come
+// after it, so `Resolver` holds records back until the depth sequence
+// decides every column.
+
+use std::collections::VecDeque;
+
+use crate::color::{BLUE, CYAN, GREEN, MAGENTA, RED, RESET, YELLOW};
+
+/// How depth is turned into indentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GuideStyle {
+    /// Plain spaces.
+    #[default]
+    Spaces,
+    /// Box-drawing guides: `│ ├── └──`.
+    Unicode,
+    /// ASCII guides: `| +-- \--`.
+    Ascii,
+}
+
+impl std::str::FromStr for GuideStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spaces" | "none" => Ok(GuideStyle::Spaces),
+            "unicode" | "tree" => Ok(GuideStyle::Unicode),
+            "ascii" => Ok(GuideStyle::Ascii),
+            other => Err(format!("expected spaces, unicode or ascii, got `{other}`")),
+        }
+    }
+}
+
+struct Glyphs {
+    vert: char,
+    tee: char,
+    elbow: char,
+    horiz: char,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    vert: '│',
+    tee: '├',
+    elbow: '└',
+    horiz: '─',
+};
+
+const ASCII: Glyphs = Glyphs {
+    vert: '|',
+    tee: '+',
+    elbow: '\\',
+    horiz: '-',
+};
+
+/// Guide colors, cycled per depth level.
+const LEVEL_COLORS: [&str; 6] = [BLUE, MAGENTA, CYAN, GREEN, YELLOW, RED];
+
+/// Draws guide prefixes for one style, indent width and color setting.
+#[derive(Debug, Clone, Copy)]
+pub struct GuidePainter {
+    pub style: GuideStyle,
+    pub width: usize,
+    pub color: bool,
+}
+
+impl GuidePainter {
+    fn glyphs(&self) -> &'static Glyphs {
+        match self.style {
+            GuideStyle::Ascii => &ASCII,
+            _ => &UNICODE,
+        }
+    }
+
+    /// One `width`-column cell for depth `level` (1-based).
+    fn cell(&self, out: &mut String, level: usize, head: Option<char>, trunk: bool) {
+        if self.width == 0 {
+            return;
+        }
+        let g = self.glyphs();
+        let mut cell = String::new();
+        match head {
+            Some(c) => {
+                cell.push(c);
+                let horiz = self
+                    .width
+                    .saturating_sub(2)
+                    .max(usize::from(self.width == 2));
+                cell.extend(std::iter::repeat_n(g.horiz, horiz));
+            }
+            None => cell.push(if trunk { g.vert } else { ' ' }),
+        }
+        let used = cell.chars().count();
+        if self.color && (head.is_some() || trunk) {
+            let color = LEVEL_COLORS[(level - 1) % LEVEL_COLORS.len()];
+            out.push_str(color);
+            out.push_str(&cell);
+            out.push_str(RESET);
+        } else {
+            out.push_str(&cell);
+        }
+        out.extend(std::iter::repeat_n(' ', self.width - used));
+    }
+
+    /// Prefix of the line of a record at `depth`. `cont[l - 1]` tells
+    /// whether depth level `l` continues below this line.
+    pub fn record_prefix(&self, depth: usize, cont: &[bool]) -> String {
+        let mut out = String::new();
+        for level in 1..=depth {
+            if level == depth {
+                let g = self.glyphs();
+                let head = if cont[level - 1] { g.tee } else { g.elbow };
+                self.cell(&mut out, level, Some(head), true);
+            } else {
+                self.cell(&mut out, level, None, cont[level - 1]);
+            }
+        }
+        out
+    }
+
+    /// Prefix of a line that belongs to a record at `depth` without being
+    /// its first line: trunks only, for levels `1..=levels`.
+    pub fn trunk_prefix(&self, levels: usize, cont: &[bool]) -> String {
+        let mut out = String::new();
+        for level in 1..=levels {
+            self.cell(
+                &mut out,
+                level,
+                None,
+                cont.get(level - 1).copied().unwrap_or(false),
+            );
+        }
+        out
+    }
+}
+
+/// Items are never indented deeper than this, whatever depth they arrive
+/// with: a bogus `depth=` must not cost one guide column per level.
+pub const MAX_DEPTH: usize = 256;
+
+struct Pending<T> {
+    depth: usize,
+    /// Continuation of levels `1..=depth + 1`; `None` until decided. The
+    /// extra level says whether the record has children.
+    cont: Vec<Option<bool>>,
+    /// Smallest depth among the records pushed after this one.
+    min_after: usize,
+    item: T,
+}
+
+/// Holds items back until the following depths decide their guides.
+///
+/// Level `l` of an item continues iff the next record at depth `<= l` after
+/// it is exactly at depth `l`. All levels are decided once a record at depth
+/// 0 or 1 follows.
+pub struct Resolver<T> {
+    pending: VecDeque<Pending<T>>,
+    window: usize,
+}
+
+impl<T> Resolver<T> {
+    /// `window` bounds how many items may be held back; beyond it the oldest
+    /// is released with a guess (see [`Resolver::pop_guess`]).
+    pub fn new(window: usize) -> Self {
+        Resolver {
+            pending: VecDeque::new(),
+            window: window.max(1),
+        }
+    }
+
+    /// Queue `item` at `depth`, at most [`MAX_DEPTH`]. Records (`node ==
+    /// true`) decide the guides of earlier items; other lines (raw text) only
+    /// receive guides.
+    pub fn push(&mut self, depth: usize, node: bool, item: T) {
+        let depth = depth.min(MAX_DEPTH);
+        if node {
+            // `min_after` never decreases from front to back, so only a tail
+            // of the queue can be affected.
+            for p in self.pending.iter_mut().rev() {
+                if p.min_after <= depth {
+                    break;
+                }
+                let hi = (p.min_after - 1).min(p.depth + 1);
+                for level in depth.max(1)..=hi {
+                    p.cont[level - 1] = Some(level == depth);
+                }
+                p.min_after = depth;
+            }
+        }
+        self.pending.push_back(Pending {
+            depth,
+            cont: vec![None; depth + 1],
+            min_after: usize::MAX,
+            item,
+        });
+    }
+
+    /// The oldest item, once its guides are decided or the window is full.
+    pub fn pop_ready(&mut self) -> Option<(T, Vec<bool>)> {
+        let front = self.pending.front()?;
+        if front.min_after <= 1 {
+            self.pop_with(false)
+        } else if self.pending.len() > self.window {
+            self.pop_guess()
+        } else {
+            None
+        }
+    }
+
+    /// The oldest item, guessing undecided levels: ancestors continue, no
+    /// children. Used when input stalls and for window overflow.
+    pub fn pop_guess(&mut self) -> Option<(T, Vec<bool>)> {
+        self.pop_with(true)
+    }
+
+    /// The oldest item with undecided levels closed: nothing follows.
+    pub fn pop_final(&mut self) -> Option<(T, Vec<bool>)> {
+        self.pop_with(false)
+    }
+
+    fn pop_with(&mut self, guess: bool) -> Option<(T, Vec<bool>)> {
+        let p = self.pending.pop_front()?;
+        let cont = p
+            .cont
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c.unwrap_or(guess && i < p.depth))
+            .collect();
+        Some((p.item, cont))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Push records at `depths` (named by index), then drain what is ready.
+    fn resolve(depths: &[usize]) -> (Resolver<usize>, Vec<(usize, Vec<bool>)>) {
+        let mut r = Resolver::new(100);
+        let mut ready = Vec::new();
+        for (i, &depth) in depths.iter().enumerate() {
+            r.push(depth, true, i);
+            while let Some(item) = r.pop_ready() {
+                ready.push(item);
+            }
+        }
+        (r, ready)
+    }
+
+    #[test]
+    fn later_depths_decide_the_guides() {
+        let (mut r, ready) = resolve(&[0, 1, 2, 1, 0]);
+        assert_eq!(
+            ready,
+            [
+                (0, vec![true]),
+                (1, vec![true, true]),
+                (2, vec![true, false, false]),
+                (3, vec![false, false]),
+            ]
+        );
+        assert_eq!(r.pop_ready(), None);
+        assert_eq!(r.pop_final(), Some((4, vec![false])));
+        assert_eq!(r.pop_final(), None);
+    }
+
+    #[test]
+    fn held_until_a_shallow_record_follows() {
+        let (mut r, ready) = resolve(&[1, 2, 3]);
+        assert!(ready.is_empty());
+        // Guessing: ancestors continue, nothing has children.
+        assert_eq!(r.pop_guess(), Some((0, vec![true, true])));
+        assert_eq!(r.pop_guess(), Some((1, vec![true, true, true])));
+        assert_eq!(r.pop_guess(), Some((2, vec![true, true, true, false])));
+    }
+
+    #[test]
+    fn raw_lines_take_guides_without_deciding() {
+        let mut r = Resolver::new(100);
+        r.push(1, true, 0);
+        r.push(2, false, 1);
+        r.push(1, true, 2);
+        assert_eq!(r.pop_ready(), Some((0, vec![true, false])));
+        assert_eq!(r.pop_ready(), Some((1, vec![true, false, false])));
+    }
+
+    #[test]
+    fn window_overflow_releases_the_oldest() {
+        let mut r = Resolver::new(2);
+        r.push(1, true, 0);
+        r.push(2, true, 1);
+        assert_eq!(r.pop_ready(), None);
+        r.push(3, true, 2);
+        assert_eq!(r.pop_ready(), Some((0, vec![true, true])));
+        assert_eq!(r.pop_ready(), None);
+    }
+
+    #[test]
+    fn huge_depths_are_clamped() {
+        let mut r = Resolver::new(100);
+        r.push(usize::MAX, true, 0);
+        r.push(1_000_000_000, false, 1);
+        let (_, cont) = r.pop_final().unwrap();
+        assert_eq!(cont.len(), MAX_DEPTH + 1);
+        let (_, cont) = r.pop_final().unwrap();
+        assert_eq!(cont.len(), MAX_DEPTH + 1);
+    }
+}
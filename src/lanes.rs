@@ -0,0 +1,176 @@
+// ---------- labelled lanes ----------
+//
+// Several independent renderers sharing one output, e.g. one per merged
+// input file or per thread: each lane keeps its own depth, call timing and
+// pruning state, so interleaved call trees cannot disturb each other, and
+// every line it writes starts with the lane's label.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::color::{BLUE, BOLD, CYAN, GREEN, MAGENTA, RED, RESET, YELLOW};
+use crate::record::Record;
+use crate::render::{RenderConfig, Renderer};
+use crate::time::TimestampParser;
+
+/// Label colors, cycled per lane.
+const LANE_COLORS: [&str; 6] = [CYAN, MAGENTA, YELLOW, GREEN, BLUE, RED];
+
+struct Lane {
+    label: String,
+    renderer: Renderer,
+}
+
+/// One [`Renderer`] per lane, all with the same configuration. Labels are
+/// padded to the longest one so far.
+pub struct Lanes {
+    config: RenderConfig,
+    lanes: Vec<Lane>,
+    width: usize,
+}
+
+impl Lanes {
+    pub fn new(config: &RenderConfig) -> Self {
+        Lanes {
+            config: config.clone(),
+            lanes: Vec::new(),
+            width: 0,
+        }
+    }
+
+    /// Open a lane for part of the stream the configuration reads; returns
+    /// its index.
+    pub fn add(&mut self, label: &str) -> usize {
+        self.add_input(label, self.config.timestamps.clone())
+    }
+
+    /// Open a lane for a stream of its own, whose timestamps `timestamps`
+    /// reads; returns its index.
+    pub fn add_input(&mut self, label: &str, timestamps: TimestampParser) -> usize {
+        self.width = self.width.max(label.chars().count());
+        let config = RenderConfig {
+            timestamps,
+            ..self.config.clone()
+        };
+        self.lanes.push(Lane {
+            label: label.to_string(),
+            renderer: Renderer::new(config),
+        });
+        self.lanes.len() - 1
+    }
+
+    pub fn write_record<W: Write>(
+        &mut self,
+        w: &mut W,
+        lane: usize,
+        rec: &Record,
+    ) -> io::Result<()> {
+        let prefix = self.prefix(lane);
+        let renderer = &mut self.lanes[lane].renderer;
+        renderer.write_record(&mut Prefixed::new(w, &prefix), rec)
+    }
+
+    /// [`Renderer::write_hidden`] in `lane`.
+    pub fn write_hidden<W: Write>(
+        &mut self,
+        w: &mut W,
+        lane: usize,
+        rec: &Record,
+    ) -> io::Result<()> {
+        let prefix = self.prefix(lane);
+        let renderer = &mut self.lanes[lane].renderer;
+        renderer.write_hidden(&mut Prefixed::new(w, &prefix), rec)
+    }
+
+    pub fn write_raw<W: Write>(&mut self, w: &mut W, lane: usize, text: &str) -> io::Result<()> {
+        let prefix = self.prefix(lane);
+        let renderer = &mut self.lanes[lane].renderer;
+        renderer.write_raw(&mut Prefixed::new(w, &prefix), text)
+    }
+
+    /// [`Renderer::flush_pending`] for every lane.
+    pub fn flush_pending<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        for lane in 0..self.lanes.len() {
+            let prefix = self.prefix(lane);
+            let renderer = &mut self.lanes[lane].renderer;
+            renderer.flush_pending(&mut Prefixed::new(w, &prefix))?;
+        }
+        Ok(())
+    }
+
+    /// [`Renderer::finish`] for every lane, in order.
+    pub fn finish<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        for lane in 0..self.lanes.len() {
+            let prefix = self.prefix(lane);
+            let renderer = &mut self.lanes[lane].renderer;
+            renderer.finish(&mut Prefixed::new(w, &prefix))?;
+        }
+        Ok(())
+    }
+
+    /// The padded, colored label plus a space.
+    fn prefix(&self, lane: usize) -> String {
+        let label = &self.lanes[lane].label;
+        let width = self.width;
+        if self.config.color {
+            let color = LANE_COLORS[lane % LANE_COLORS.len()];
+            format!("{BOLD}{color}{label:<width$}{RESET} ")
+        } else {
+            format!("{label:<width$} ")
+        }
+    }
+}
+
+/// Short labels for input paths: the file name without its extension
+/// (`logs/server.log` → `server`), or the whole path when that would be
+/// ambiguous. `-` is `stdin`.
+pub fn input_labels(paths: &[String]) -> Vec<String> {
+    let short = |p: &str| -> String {
+        if p == "-" {
+            return "stdin".to_string();
+        }
+        Path::new(p)
+            .file_stem()
+            .map_or_else(|| p.to_string(), |s| s.to_string_lossy().into_owned())
+    };
+    let labels: Vec<String> = paths.iter().map(|p| short(p)).collect();
+    let unique = labels
+        .iter()
+        .enumerate()
+        .all(|(i, l)| !labels[..i].contains(l));
+    if unique { labels } else { paths.to_vec() }
+}
+
+/// A writer that starts every line with `prefix`.
+struct Prefixed<'a, W> {
+    inner: &'a mut W,
+    prefix: &'a str,
+    line_start: bool,
+}
+
+impl<'a, W: Write> Prefixed<'a, W> {
+    fn new(inner: &'a mut W, prefix: &'a str) -> Self {
+        Prefixed {
+            inner,
+            prefix,
+            line_start: true,
+        }
+    }
+}
+
+impl<W: Write> Write for Prefixed<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for piece in buf.split_inclusive(|&b| b == b'\n') {
+            if self.line_start {
+                self.inner.write_all(self.prefix.as_bytes())?;
+            }
+            self.inner.write_all(piece)?;
+            self.line_start = piece.ends_with(b"\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
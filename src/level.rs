@@ -0,0 +1,238 @@
+// ---------- log levels ----------
+
+use crate::glob::Glob;
+use crate::record::Record;
+
+/// Map a `level=` value to the single letter shown in the `[X]` column.
+pub fn map_level(level: &str) -> char {
+    match level {
+        "info" => 'I',
+        "warn" | "warning" => 'W',
+        "error" => 'E',
+        "debug" => 'D',
+        "trace" => 'T',
+        other if !other.is_empty() => other.chars().next().unwrap_or('?').to_ascii_uppercase(),
+        _ => '?',
+    }
+}
+
+// ---------- level filtering ----------
+
+/// A known severity, most severe first: a threshold of `Info` lets `Error`,
+/// `Warn` and `Info` through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    /// The severity of a `level=` value, by the same letter as
+    /// [`map_level`]; `None` for anything else.
+    pub fn from_value(level: &str) -> Option<Level> {
+        match map_level(level) {
+            'E' => Some(Level::Error),
+            'W' => Some(Level::Warn),
+            'I' => Some(Level::Info),
+            'D' => Some(Level::Debug),
+            'T' => Some(Level::Trace),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a threshold: a level name, or `off` (`None`) to let nothing through.
+fn parse_threshold(s: &str) -> Result<Option<Level>, String> {
+    match s {
+        "off" => Ok(None),
+        "error" => Ok(Some(Level::Error)),
+        "warn" | "warning" => Ok(Some(Level::Warn)),
+        "info" => Ok(Some(Level::Info)),
+        "debug" => Ok(Some(Level::Debug)),
+        "trace" => Ok(Some(Level::Trace)),
+        other => Err(format!(
+            "expected off, error, warn, info, debug or trace, got `{other}`"
+        )),
+    }
+}
+
+/// Which field a [`LevelFilter`] directive looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Func,
+    File,
+}
+
+#[derive(Debug, Clone)]
+struct Directive {
+    target: Target,
+    pattern: Glob,
+    threshold: Option<Level>,
+}
+
+/// RUST_LOG-style level thresholds, parsed from a comma-separated list:
+///
+///   warn                    everything at warn or more severe
+///   btree_*=trace           functions matching the glob, down to trace
+///   func=btree_*:trace      the same, spelled out
+///   file=heap_file.c:debug  records from matching files, down to debug
+///   lock_*=off              nothing from these functions
+///
+/// A record is judged by the most specific directive matching its `func` or
+/// `file` (no wildcards beats wildcards, then more literal characters, then
+/// the later directive), or else by the bare level, which defaults to
+/// `trace`. Records without a known level count as `info`.
+#[derive(Debug, Clone)]
+pub struct LevelFilter {
+    default: Option<Level>,
+    directives: Vec<Directive>,
+}
+
+impl Default for LevelFilter {
+    fn default() -> Self {
+        LevelFilter {
+            default: Some(Level::Trace),
+            directives: Vec::new(),
+        }
+    }
+}
+
+impl LevelFilter {
+    /// Whether `rec` passes.
+    pub fn enabled(&self, rec: &Record) -> bool {
+        let level = rec
+            .level
+            .as_deref()
+            .and_then(Level::from_value)
+            .unwrap_or(Level::Info);
+        let mut best: Option<&Directive> = None;
+        for d in &self.directives {
+            let field = match d.target {
+                Target::Func => rec.func.as_deref(),
+                Target::File => rec.file.as_deref(),
+            };
+            if field.is_some_and(|f| d.pattern.matches(f))
+                && best.is_none_or(|b| d.specificity() >= b.specificity())
+            {
+                best = Some(d);
+            }
+        }
+        let threshold = best.map_or(self.default, |d| d.threshold);
+        threshold.is_some_and(|max| level <= max)
+    }
+
+    /// Whether every record passes, so the filter can be skipped.
+    pub fn is_everything(&self) -> bool {
+        self.default == Some(Level::Trace)
+            && self
+                .directives
+                .iter()
+                .all(|d| d.threshold == Some(Level::Trace))
+    }
+}
+
+impl Directive {
+    fn specificity(&self) -> (bool, usize) {
+        (self.pattern.is_literal(), self.pattern.literal_len())
+    }
+}
+
+impl std::str::FromStr for LevelFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = LevelFilter::default();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let Some((key, rest)) = item.split_once('=') else {
+                filter.default = parse_threshold(item)?;
+                continue;
+            };
+            let (target, pattern, level) = match (key, rest.rsplit_once(':')) {
+                ("func", Some((pattern, level))) => (Target::Func, pattern, level),
+                ("file", Some((pattern, level))) => (Target::File, pattern, level),
+                ("func" | "file", None) => {
+                    return Err(format!("expected `{key}=PATTERN:LEVEL`, got `{item}`"));
+                }
+                (pattern, _) => (Target::Func, pattern, rest),
+            };
+            if pattern.is_empty() {
+                return Err(format!("empty pattern in `{item}`"));
+            }
+            filter.directives.push(Directive {
+                target,
+                pattern: Glob::new(pattern),
+                threshold: parse_threshold(level)?,
+            });
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(level: &str, func: &str, file: &str) -> Record {
+        Record {
+            level: Some(level.to_string()),
+            func: Some(func.to_string()),
+            file: Some(file.to_string()),
+            ..Record::default()
+        }
+    }
+
+    fn filter(s: &str) -> LevelFilter {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn bare_level() {
+        let f = filter("warn");
+        assert!(f.enabled(&rec("error", "f", "a.c")));
+        assert!(f.enabled(&rec("warning", "f", "a.c")));
+        assert!(!f.enabled(&rec("info", "f", "a.c")));
+        assert!(!filter("off").enabled(&rec("error", "f", "a.c")));
+        // Unknown levels count as info.
+        assert!(filter("info").enabled(&rec("notice", "f", "a.c")));
+        assert!(!filter("warn").enabled(&Record::default()));
+    }
+
+    #[test]
+    fn func_and_file_directives() {
+        let f = filter("warn,btree_*=trace,file=heap_file.c:debug,lock_*=off");
+        assert!(f.enabled(&rec("trace", "btree_insert", "a.c")));
+        assert!(!f.enabled(&rec("trace", "heap_insert", "heap_file.c")));
+        assert!(f.enabled(&rec("debug", "heap_insert", "heap_file.c")));
+        assert!(!f.enabled(&rec("error", "lock_wait", "a.c")));
+        assert!(!f.enabled(&rec("info", "main", "a.c")));
+        assert!(filter("func=btree_*:trace").enabled(&rec("trace", "btree_x", "a.c")));
+    }
+
+    #[test]
+    fn most_specific_directive_wins() {
+        // A literal beats any wildcard, whatever the order.
+        let f = filter("btree_insert=off,btree_*=trace");
+        assert!(!f.enabled(&rec("error", "btree_insert", "a.c")));
+        assert!(f.enabled(&rec("trace", "btree_split", "a.c")));
+        // More literal characters win among wildcards.
+        let f = filter("btree_s*=error,b*=trace");
+        assert!(!f.enabled(&rec("warn", "btree_split", "a.c")));
+        // Equally specific: the later one.
+        let f = filter("b*=error,x*=trace,b*=trace");
+        assert!(f.enabled(&rec("trace", "btree_split", "a.c")));
+    }
+
+    #[test]
+    fn everything_and_errors() {
+        assert!(filter("").is_everything());
+        assert!(filter("trace,x=trace").is_everything());
+        assert!(!filter("x=debug").is_everything());
+        assert!("loud".parse::<LevelFilter>().is_err());
+        assert!("func=x".parse::<LevelFilter>().is_err());
+        assert!("=debug".parse::<LevelFilter>().is_err());
+        assert!("x=loud".parse::<LevelFilter>().is_err());
+    }
+}
@@ -0,0 +1,43 @@
+// depthlog: parse and pretty-print depth-annotated logfmt logs.
+//
+// The pipeline is: a line of logfmt text is parsed into a `Record`
+// (`logfmt` + `record`), and a `Renderer` turns records into the indented,
+// optionally colorized text that `depthlog_pretty` prints. `stream` drives
+// that pipeline line by line over a reader/writer pair (merging several
+// inputs by time, or splitting threads, into `lanes` when asked), and `cli` is the
+// `depthlog` command built on top of it; `viewer` and `terminal` back its
+// interactive `view` subcommand.
+//
+//   use depthlog_rust::{Record, RenderConfig, Renderer};
+//
+//   let rec = Record::parse(r#"ts=2025-02-15T09:12:01.123Z level=info depth=1 func=f msg="hi""#)?;
+//   let mut r = Renderer::new(RenderConfig::default());
+//   print!("{}", r.render_to_string(&rec));
+
+pub mod calltree;
+pub mod cli;
+pub mod color;
+pub mod convert;
+pub mod follow;
+pub mod glob;
+pub mod guides;
+pub mod lanes;
+pub mod level;
+pub mod logfmt;
+pub mod record;
+pub mod render;
+pub mod stats;
+pub mod stream;
+pub mod subtree;
+pub mod terminal;
+pub mod threads;
+pub mod time;
+pub mod timing;
+pub mod tz;
+pub mod viewer;
+
+pub use calltree::{CallTree, CallTreeBuilder, Node, NodeId};
+pub use logfmt::{DuplicatePolicy, Fields, ParseError, ParseErrorKind, parse_logfmt};
+pub use record::Record;
+pub use render::{Multiline, RenderConfig, Renderer};
+pub use stream::stream_pretty;
@@ -0,0 +1,475 @@
+// ---------- logfmt fields ----------
+
+use std::fmt;
+
+/// Why a line could not be turned into fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the line where parsing gave up; `None` for errors
+    /// found after tokenizing (duplicate keys).
+    pub pos: Option<usize>,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A word with no `=` after it, e.g. the `thread` of a panic message.
+    MissingEquals(String),
+    /// `=value` with nothing before the `=`.
+    EmptyKey,
+    /// A quoted value that runs to the end of the line.
+    UnterminatedQuote,
+    /// Something other than whitespace right after a closing quote.
+    TrailingAfterQuote,
+    /// A key occurred twice under [`DuplicatePolicy::Error`].
+    DuplicateKey(String),
+}
+
+impl ParseError {
+    fn at(pos: usize, kind: ParseErrorKind) -> Self {
+        ParseError {
+            pos: Some(pos),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(pos) = self.pos {
+            write!(f, "byte {pos}: ")?;
+        }
+        match &self.kind {
+            ParseErrorKind::MissingEquals(word) => write!(f, "expected `=` after `{word}`"),
+            ParseErrorKind::EmptyKey => f.write_str("empty key"),
+            ParseErrorKind::UnterminatedQuote => f.write_str("unterminated quoted value"),
+            ParseErrorKind::TrailingAfterQuote => {
+                f.write_str("expected whitespace after closing quote")
+            }
+            ParseErrorKind::DuplicateKey(key) => write!(f, "duplicate key `{key}`"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// ---------- ordered fields ----------
+
+/// What to do when a key occurs more than once on a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Keep the first value.
+    FirstWins,
+    /// Keep the last value (what a map insert would do).
+    #[default]
+    LastWins,
+    /// Keep every occurrence.
+    KeepAll,
+    /// Reject the line with [`ParseErrorKind::DuplicateKey`].
+    Error,
+}
+
+impl std::str::FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(DuplicatePolicy::FirstWins),
+            "last" => Ok(DuplicatePolicy::LastWins),
+            "all" => Ok(DuplicatePolicy::KeepAll),
+            "error" => Ok(DuplicatePolicy::Error),
+            other => Err(format!("expected first, last, all or error, got `{other}`")),
+        }
+    }
+}
+
+/// `key=value` pairs in input order; a key may occur more than once.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fields {
+    pairs: Vec<(String, String)>,
+}
+
+impl Fields {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, key: impl Into<String>, val: impl Into<String>) {
+        self.pairs.push((key.into(), val.into()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (String, String)> {
+        self.pairs.iter()
+    }
+
+    /// The first value of `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Every value of `key`, in input order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.pairs.iter().any(|(k, _)| k == key)
+    }
+
+    /// Resolve repeated keys according to `policy`.
+    ///
+    /// Every surviving pair keeps its position: `FirstWins` keeps the first
+    /// occurrence in place, `LastWins` the last one.
+    pub fn apply(self, policy: DuplicatePolicy) -> Result<Fields, ParseError> {
+        let pairs = self.pairs;
+        let seen_before = |i: usize| pairs[..i].iter().any(|(k, _)| *k == pairs[i].0);
+        let seen_after = |i: usize| pairs[i + 1..].iter().any(|(k, _)| *k == pairs[i].0);
+        let keep: Vec<bool> = match policy {
+            DuplicatePolicy::KeepAll => return Ok(Fields { pairs }),
+            DuplicatePolicy::Error => {
+                if let Some(i) = (0..pairs.len()).find(|&i| seen_before(i)) {
+                    return Err(ParseError {
+                        pos: None,
+                        kind: ParseErrorKind::DuplicateKey(pairs[i].0.clone()),
+                    });
+                }
+                return Ok(Fields { pairs });
+            }
+            DuplicatePolicy::FirstWins => (0..pairs.len()).map(|i| !seen_before(i)).collect(),
+            DuplicatePolicy::LastWins => (0..pairs.len()).map(|i| !seen_after(i)).collect(),
+        };
+        let pairs = pairs
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(p, k)| k.then_some(p))
+            .collect();
+        Ok(Fields { pairs })
+    }
+}
+
+impl FromIterator<(String, String)> for Fields {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Fields {
+            pairs: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for Fields {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Fields {
+    type Item = &'a (String, String);
+    type IntoIter = std::slice::Iter<'a, (String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.iter()
+    }
+}
+
+impl fmt::Display for Fields {
+    /// The pairs as a logfmt line, in order.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        for (k, v) in self {
+            push_pair(&mut out, k, v);
+        }
+        f.write_str(&out)
+    }
+}
+
+// ---------- logfmt parsing ----------
+
+/// Parse one logfmt line into its `key=value` pairs, in input order, keeping
+/// every occurrence of a repeated key (see [`Fields::apply`]).
+///
+/// Values may be bare (`depth=3`) or double-quoted with backslash escapes
+/// (`msg="a \"b\"\n"`, `\u00e9`, surrogate pairs for `\ud83d\ude00`).
+/// Non-ASCII text is kept as is.
+pub fn parse_logfmt(input: &str) -> Result<Fields, ParseError> {
+    let mut m = Fields::new();
+    let bytes = input.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= bytes.len() {
+            break;
+        }
+
+        let key_start = i;
+        while i < bytes.len() && bytes[i] != b'=' && !bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= bytes.len() || bytes[i] != b'=' {
+            let word = input[key_start..i].to_string();
+            return Err(ParseError::at(i, ParseErrorKind::MissingEquals(word)));
+        }
+        if i == key_start {
+            return Err(ParseError::at(i, ParseErrorKind::EmptyKey));
+        }
+        let key = &input[key_start..i];
+        i += 1;
+
+        let val = if i < bytes.len() && bytes[i] == b'"' {
+            let quote_start = i;
+            let mut closed = false;
+            i += 1;
+            let mut v = String::new();
+            while i < bytes.len() {
+                // Copy everything up to the next quote or backslash at once.
+                // Both are ASCII, so the slice always ends on a char boundary
+                // and multi-byte UTF-8 passes through untouched.
+                let run = bytes[i..]
+                    .iter()
+                    .position(|&b| b == b'"' || b == b'\\')
+                    .unwrap_or(bytes.len() - i);
+                v.push_str(&input[i..i + run]);
+                i += run;
+                if i >= bytes.len() {
+                    break;
+                }
+                if bytes[i] == b'"' {
+                    i += 1;
+                    closed = true;
+                    break;
+                }
+                i += 1;
+                let Some(esc) = input[i..].chars().next() else {
+                    break;
+                };
+                i += esc.len_utf8();
+                match esc {
+                    '"' => v.push('"'),
+                    '\\' => v.push('\\'),
+                    'n' => v.push('\n'),
+                    't' => v.push('\t'),
+                    'r' => v.push('\r'),
+                    'u' => {
+                        let (c, used) = decode_unicode_escape(&input[i..]);
+                        v.push(c);
+                        i += used;
+                    }
+                    _ => v.push(esc),
+                }
+            }
+            if !closed {
+                return Err(ParseError::at(
+                    quote_start,
+                    ParseErrorKind::UnterminatedQuote,
+                ));
+            }
+            if i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                return Err(ParseError::at(i, ParseErrorKind::TrailingAfterQuote));
+            }
+            v
+        } else {
+            let val_start = i;
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            input[val_start..i].to_string()
+        };
+
+        m.push(key, val);
+    }
+
+    Ok(m)
+}
+
+/// Decode the `XXXX` after `\u` at the start of `rest`, including a
+/// following `\uXXXX` low surrogate. Returns the character and the number
+/// of bytes consumed. Without four hex digits the escape is just a literal
+/// `u`; an unpaired surrogate becomes U+FFFD.
+fn decode_unicode_escape(rest: &str) -> (char, usize) {
+    let hex4 = |s: &str| -> Option<u32> {
+        let digits = s.get(..4)?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        u32::from_str_radix(digits, 16).ok()
+    };
+
+    let Some(hi) = hex4(rest) else {
+        return ('u', 0);
+    };
+    if (0xD800..0xDC00).contains(&hi)
+        && let Some(lo) = rest[4..].strip_prefix("\\u").and_then(hex4)
+        && (0xDC00..0xE000).contains(&lo)
+    {
+        let c = 0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00);
+        return (char::from_u32(c).unwrap_or('\u{FFFD}'), 10);
+    }
+    (char::from_u32(hi).unwrap_or('\u{FFFD}'), 4)
+}
+
+// ---------- logfmt writing ----------
+
+/// Append ` key=value` (without the leading space for the first pair) to
+/// `out`, quoting and escaping the value when it would not survive a round
+/// trip through [`parse_logfmt`] as a bare word.
+pub fn push_pair(out: &mut String, key: &str, val: &str) {
+    if !out.is_empty() {
+        out.push(' ');
+    }
+    out.push_str(key);
+    out.push('=');
+    push_value(out, val);
+}
+
+fn push_value(out: &mut String, val: &str) {
+    let needs_quotes = val.is_empty()
+        || val
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '=' | '\\'));
+    if !needs_quotes {
+        out.push_str(val);
+        return;
+    }
+    out.push('"');
+    for c in val.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(fields: &Fields) -> Vec<(&str, &str)> {
+        fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_input_order_and_repeated_keys() {
+        let fields = parse_logfmt("b=1 a=2 b=3").unwrap();
+        assert_eq!(pairs(&fields), [("b", "1"), ("a", "2"), ("b", "3")]);
+        assert_eq!(fields.get("b"), Some("1"));
+        assert_eq!(fields.get_all("b").collect::<Vec<_>>(), ["1", "3"]);
+    }
+
+    #[test]
+    fn duplicate_policies() {
+        let fields = || parse_logfmt("b=1 a=2 b=3").unwrap();
+        let first = fields().apply(DuplicatePolicy::FirstWins).unwrap();
+        assert_eq!(pairs(&first), [("b", "1"), ("a", "2")]);
+        let last = fields().apply(DuplicatePolicy::LastWins).unwrap();
+        assert_eq!(pairs(&last), [("a", "2"), ("b", "3")]);
+        let all = fields().apply(DuplicatePolicy::KeepAll).unwrap();
+        assert_eq!(all.len(), 3);
+        let err = fields().apply(DuplicatePolicy::Error).unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::DuplicateKey("b".to_string()));
+        assert_eq!(err.pos, None);
+    }
+
+    #[test]
+    fn duplicate_policy_names() {
+        assert_eq!("first".parse(), Ok(DuplicatePolicy::FirstWins));
+        assert_eq!("all".parse(), Ok(DuplicatePolicy::KeepAll));
+        assert!("most".parse::<DuplicatePolicy>().is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let line = r#"ts=1 msg="a \"b\"" k="""#;
+        let fields = parse_logfmt(line).unwrap();
+        assert_eq!(fields.to_string(), line);
+        assert_eq!(parse_logfmt(&fields.to_string()).unwrap(), fields);
+    }
+
+    fn value(line: &str) -> String {
+        parse_logfmt(line).unwrap().get("v").unwrap().to_string()
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        assert_eq!(value(r#"v="caf\u00e9""#), "café");
+        assert_eq!(value(r#"v="\ud83d\ude00!""#), "😀!");
+        assert_eq!(value(r#"v="\u00E9\u00e9""#), "éé");
+    }
+
+    #[test]
+    fn broken_unicode_escapes() {
+        // Unpaired surrogates become U+FFFD; the rest is kept.
+        assert_eq!(value(r#"v="\ud83dx""#), "\u{FFFD}x");
+        assert_eq!(value(r#"v="\ude00""#), "\u{FFFD}");
+        assert_eq!(value(r#"v="\ud83dA""#), "\u{FFFD}A");
+        // Fewer than four hex digits: a literal `u`.
+        assert_eq!(value(r#"v="\u12""#), "u12");
+        assert_eq!(value(r#"v="\u""#), "u");
+    }
+
+    #[test]
+    fn non_ascii_passes_through() {
+        assert_eq!(value(r#"v="한국어 \"😀\"""#), "한국어 \"😀\"");
+        assert_eq!(value("v=é\\ü"), "é\\ü");
+        assert_eq!(value(r#"v="\é""#), "é");
+    }
+
+    fn error(line: &str) -> ParseError {
+        parse_logfmt(line).unwrap_err()
+    }
+
+    #[test]
+    fn quote_errors() {
+        let err = error(r#"a=1 msg="open"#);
+        assert_eq!(
+            (err.pos, err.kind),
+            (Some(8), ParseErrorKind::UnterminatedQuote)
+        );
+        let err = error(r#"msg="a\""#);
+        assert_eq!(err.kind, ParseErrorKind::UnterminatedQuote);
+        let err = error(r#"msg="a"b c=1"#);
+        assert_eq!(
+            (err.pos, err.kind),
+            (Some(7), ParseErrorKind::TrailingAfterQuote)
+        );
+        assert!(parse_logfmt(r#"msg="a"	c=1"#).is_ok());
+    }
+
+    #[test]
+    fn key_errors() {
+        let err = error("a=1 thread main");
+        assert_eq!(err.pos, Some(10));
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::MissingEquals("thread".to_string())
+        );
+        assert_eq!(err.to_string(), "byte 10: expected `=` after `thread`");
+        assert_eq!(error("a=1 =2").kind, ParseErrorKind::EmptyKey);
+    }
+}
@@ -1,3 +1,11 @@
-fn main() {
-    println!("Hello, world!");
+// depthlog: command-line front end for the depthlog_rust library.
+//
+// Usage:
+//   depthlog <SUBCOMMAND> [OPTIONS] [FILE...]
+//   depthlog --help
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    depthlog_rust::cli::run(std::env::args().skip(1))
 }
@@ -0,0 +1,146 @@
+// ---------- depth log records ----------
+
+use crate::logfmt::{DuplicatePolicy, Fields, ParseError, parse_logfmt, push_pair};
+
+/// Where a field of a parsed line went; see [`Record::order`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    Ts,
+    Level,
+    Depth,
+    File,
+    Line,
+    Func,
+    Msg,
+    /// The next entry of [`Record::extra`].
+    Extra,
+}
+
+/// One parsed depth-log line.
+///
+/// The well-known keys get their own fields; everything else ends up in
+/// `extra`, in input order. Missing keys are `None` (or `0` for `depth`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    pub ts: Option<String>,
+    pub level: Option<String>,
+    pub depth: usize,
+    pub file: Option<String>,
+    pub line: Option<String>,
+    pub func: Option<String>,
+    pub msg: Option<String>,
+    pub extra: Fields,
+    /// The input order of the fields, so [`Record::to_logfmt`] can re-emit
+    /// them as they came in. Empty for records built by hand.
+    pub order: Vec<Slot>,
+}
+
+impl Record {
+    /// Parse a logfmt line into a record; repeated keys keep their last value.
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        Self::parse_with(line, DuplicatePolicy::default())
+    }
+
+    /// Parse a logfmt line, resolving repeated keys with `policy`.
+    pub fn parse_with(line: &str, policy: DuplicatePolicy) -> Result<Self, ParseError> {
+        Ok(Self::from_fields(parse_logfmt(line)?.apply(policy)?))
+    }
+
+    /// Build a record from already-parsed logfmt fields.
+    ///
+    /// The first occurrence of a well-known key fills its field; any further
+    /// occurrence (only left by [`DuplicatePolicy::KeepAll`]) is kept in
+    /// `extra`. A `depth` that is not a non-negative integer is treated as
+    /// `0`.
+    pub fn from_fields(fields: Fields) -> Self {
+        let mut rec = Record::default();
+        for (key, val) in fields {
+            let (slot, field) = match key.as_str() {
+                "ts" => (Slot::Ts, &mut rec.ts),
+                "level" => (Slot::Level, &mut rec.level),
+                "file" => (Slot::File, &mut rec.file),
+                "line" => (Slot::Line, &mut rec.line),
+                "func" => (Slot::Func, &mut rec.func),
+                "msg" => (Slot::Msg, &mut rec.msg),
+                "depth" if !rec.order.contains(&Slot::Depth) => {
+                    rec.depth = val.parse::<usize>().unwrap_or(0);
+                    rec.order.push(Slot::Depth);
+                    continue;
+                }
+                _ => {
+                    rec.extra.push(key, val);
+                    rec.order.push(Slot::Extra);
+                    continue;
+                }
+            };
+            if field.is_none() {
+                *field = Some(val);
+                rec.order.push(slot);
+            } else {
+                rec.extra.push(key, val);
+                rec.order.push(Slot::Extra);
+            }
+        }
+        rec
+    }
+
+    /// First value of an extra (non well-known) field.
+    pub fn extra(&self, key: &str) -> Option<&str> {
+        self.extra.get(key)
+    }
+
+    /// Re-emit the record as a logfmt line (without trailing newline).
+    ///
+    /// Fields come out in their input order (see [`Record::order`]); fields
+    /// without a recorded position follow, well-known keys first.
+    pub fn to_logfmt(&self) -> String {
+        let mut out = String::new();
+        let mut done: Vec<Slot> = Vec::new();
+        let mut extra = self.extra.iter();
+        for &slot in &self.order {
+            if slot == Slot::Extra {
+                if let Some((k, v)) = extra.next() {
+                    push_pair(&mut out, k, v);
+                }
+            } else if !done.contains(&slot) {
+                self.push_known(&mut out, slot);
+                done.push(slot);
+            }
+        }
+        let known = [
+            Slot::Ts,
+            Slot::Level,
+            Slot::Depth,
+            Slot::File,
+            Slot::Line,
+            Slot::Func,
+            Slot::Msg,
+        ];
+        for slot in known.into_iter().filter(|s| !done.contains(s)) {
+            self.push_known(&mut out, slot);
+        }
+        for (k, v) in extra {
+            push_pair(&mut out, k, v);
+        }
+        out
+    }
+
+    fn push_known(&self, out: &mut String, slot: Slot) {
+        let (key, val) = match slot {
+            Slot::Ts => ("ts", self.ts.as_deref()),
+            Slot::Level => ("level", self.level.as_deref()),
+            Slot::Depth => {
+                push_pair(out, "depth", &self.depth.to_string());
+                return;
+            }
+            Slot::File => ("file", self.file.as_deref()),
+            Slot::Line => ("line", self.line.as_deref()),
+            Slot::Func => ("func", self.func.as_deref()),
+            Slot::Msg => ("msg", self.msg.as_deref()),
+            Slot::Extra => return,
+        };
+        if let Some(val) = val {
+            push_pair(out, key, val);
+        }
+    }
+}
@@ -0,0 +1,1070 @@
+// ---------- pretty rendering ----------
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+use crate::color::{
+    BOLD, DIM, RED, RESET, YELLOW, color_func, color_heat, color_level, visible_width,
+};
+use crate::glob::Glob;
+use crate::guides::{GuidePainter, GuideStyle, MAX_DEPTH, Resolver};
+use crate::level::map_level;
+use crate::logfmt::push_pair;
+use crate::record::Record;
+use crate::subtree::{Pruned, Pruner, Selection, SubtreeFilter};
+use crate::time::{TimeFormat, TimeMode, Timestamp, TimestampParser, format_day, format_duration};
+use crate::timing::{CallTimer, CallTiming};
+
+/// How messages containing newlines are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Multiline {
+    /// Continuation lines start under the first character of the message.
+    #[default]
+    Indent,
+    /// Keep the record on one line, newlines shown as `⏎`.
+    Collapse,
+}
+
+impl std::str::FromStr for Multiline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "indent" => Ok(Multiline::Indent),
+            "collapse" => Ok(Multiline::Collapse),
+            other => Err(format!("expected indent or collapse, got `{other}`")),
+        }
+    }
+}
+
+/// Knobs for [`Renderer`].
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    /// Emit ANSI colors for the level and function name.
+    pub color: bool,
+    /// Columns of indentation per depth level.
+    pub indent_width: usize,
+    /// Plain spaces or tree guide lines for the indentation.
+    pub guides: GuideStyle,
+    /// With guides, how many records may be held back waiting for the depth
+    /// sequence to decide between `├` and `└`.
+    pub guide_window: usize,
+    /// Show the `HH:MM:SS.mmm` time.
+    pub show_time: bool,
+    /// Absolute or elapsed, zone, precision and date of the time. With
+    /// colors, elapsed times are colored by the time since the previous
+    /// record.
+    pub time_format: TimeFormat,
+    /// Reads the `ts` values; a clone of the stream's parser (see
+    /// [`crate::stream::ReadOptions::timestamps`]).
+    pub timestamps: TimestampParser,
+    /// Write a `⋮ 1.250s gap` line before records at least this many
+    /// nanoseconds after the previous one.
+    pub gap_threshold: Option<i128>,
+    /// Write a `── YYYY-MM-DD ──` line when the date changes.
+    pub day_separator: bool,
+    /// Show the `[L]` level.
+    pub show_level: bool,
+    /// Show `file:line`.
+    pub show_location: bool,
+    /// Append the extra (non well-known) fields after the message as dimmed
+    /// `key=value` pairs, in input order.
+    pub show_extra: bool,
+    /// When non-empty, only these extra keys are appended.
+    pub extra_include: Vec<String>,
+    /// Extra keys that are never appended.
+    pub extra_exclude: Vec<String>,
+    /// Extra keys shown as `key=value` columns right after the level instead
+    /// of after the message. Each column grows to the widest value seen.
+    pub columns: Vec<String>,
+    /// Layout of multi-line messages.
+    pub multiline: Multiline,
+    /// Write a `← func 12.4ms` line where each call returns.
+    pub durations: bool,
+    /// Calls taking at least this many nanoseconds are highlighted.
+    pub slow_threshold: Option<i128>,
+    /// Only show the invocations of matching functions and what they call,
+    /// each rebased to depth 0.
+    pub subtree: Option<Glob>,
+    /// With `subtree`, write the callers of each match above it.
+    pub breadcrumb: bool,
+    /// Invocations of matching functions show only their entry line, then
+    /// how many records were hidden and how long the call took.
+    pub prune: Vec<Glob>,
+    /// Records shallower than this are not shown.
+    pub min_depth: Option<usize>,
+    /// Records deeper than this are not shown.
+    pub max_depth: Option<usize>,
+    /// Shift depths so the shallowest shown one is at the left margin:
+    /// by `min_depth` when set, else by the smallest depth seen so far.
+    pub rebase: bool,
+    /// Indent at most this many levels; deeper records are marked `[d=N]`
+    /// instead.
+    pub depth_cap: Option<usize>,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            color: false,
+            indent_width: 4,
+            guides: GuideStyle::default(),
+            guide_window: 4096,
+            show_time: true,
+            time_format: TimeFormat::default(),
+            timestamps: TimestampParser::default(),
+            day_separator: false,
+            gap_threshold: None,
+            show_level: true,
+            show_location: true,
+            show_extra: true,
+            extra_include: Vec::new(),
+            extra_exclude: Vec::new(),
+            columns: Vec::new(),
+            multiline: Multiline::default(),
+            durations: false,
+            slow_threshold: None,
+            subtree: None,
+            breadcrumb: false,
+            prune: Vec::new(),
+            min_depth: None,
+            max_depth: None,
+            rebase: false,
+            depth_cap: None,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// The trailing ` key=value ...` part of `rec`, as the extra settings
+    /// choose, or an empty string.
+    pub(crate) fn extra(&self, rec: &Record) -> String {
+        if !self.show_extra {
+            return String::new();
+        }
+        let mut pairs = String::new();
+        for (key, val) in &rec.extra {
+            let wanted = (self.extra_include.is_empty() || self.extra_include.contains(key))
+                && !self.extra_exclude.contains(key)
+                && !self.columns.contains(key);
+            if wanted {
+                push_pair(&mut pairs, key, val);
+            }
+        }
+        if pairs.is_empty() {
+            pairs
+        } else if self.color {
+            format!(" {DIM}{pairs}{RESET}")
+        } else {
+            format!(" {pairs}")
+        }
+    }
+}
+
+/// Turns records into the one-line-per-record pretty format:
+///
+///   HH:MM:SS.mmm [L] [columns] file:line | <indent>func: msg [extra]
+///
+/// With tree guides, output lags behind input: a record is written once
+/// later records have decided its guides. Raw lines always wait for the
+/// next record. Call [`Renderer::flush_pending`] when input stalls and
+/// [`Renderer::finish`] at the end.
+pub struct Renderer {
+    config: RenderConfig,
+    column_widths: Vec<usize>,
+    /// Depth of the last record, where raw lines get indented to.
+    depth: usize,
+    /// Whether the last record was outside the depth window; raw lines
+    /// after it are dropped too.
+    outside: bool,
+    /// Smallest depth seen, for `rebase` without `min_depth`.
+    base: Option<usize>,
+    /// Date of the last timestamped record, for `day_separator`.
+    day: Option<i64>,
+    /// Timestamps of the first and the previous shown record.
+    first_ts: Option<Timestamp>,
+    prev_ts: Option<Timestamp>,
+    /// Width of the time column; other lines are padded to it.
+    time_width: usize,
+    /// Width of the part before `|`, the widest record head so far. Every
+    /// line is padded to it, so the `|` only moves when a wider head comes.
+    head_width: usize,
+    held: Resolver<Held>,
+    /// Without guides, raw lines and their depth, waiting for the next
+    /// record to settle the head width.
+    raw: Vec<(String, usize)>,
+    timer: CallTimer,
+    subtree: Option<SubtreeFilter>,
+    pruner: Option<Pruner>,
+}
+
+/// A line waiting for its tree guides.
+enum Held {
+    /// With its time column, fixed when it arrived.
+    Record(Record, String),
+    Raw(String),
+    Return(CallTiming),
+    Breadcrumb(Vec<String>),
+    Pruned(Pruned),
+    Separator(String),
+    Gap(i128),
+}
+
+impl Renderer {
+    pub fn new(config: RenderConfig) -> Self {
+        // Start every column wide enough for `key=` plus a short value.
+        let column_widths = config.columns.iter().map(|k| k.len() + 2).collect();
+        let held = Resolver::new(config.guide_window);
+        let subtree = config.subtree.clone().map(SubtreeFilter::new);
+        let time_width = config.time_format.placeholder().chars().count();
+        let pruner = (!config.prune.is_empty())
+            .then(|| Pruner::new(config.prune.clone(), config.timestamps.clone()));
+        let timer = CallTimer::new(config.timestamps.clone());
+        Renderer {
+            config,
+            column_widths,
+            depth: 0,
+            outside: false,
+            base: None,
+            day: None,
+            first_ts: None,
+            prev_ts: None,
+            time_width,
+            head_width: 0,
+            held,
+            raw: Vec::new(),
+            timer,
+            subtree,
+            pruner,
+        }
+    }
+
+    pub fn config(&self) -> &RenderConfig {
+        &self.config
+    }
+
+    fn painter(&self) -> Option<GuidePainter> {
+        (self.config.guides != GuideStyle::Spaces).then_some(GuidePainter {
+            style: self.config.guides,
+            width: self.config.indent_width,
+            color: self.config.color,
+        })
+    }
+
+    /// Write the pretty form of `rec`, including the trailing newline (or
+    /// queue it, with tree guides).
+    pub fn write_record<W: Write>(&mut self, w: &mut W, rec: &Record) -> io::Result<()> {
+        self.take_record(w, rec, true)
+    }
+
+    /// Account for a record the reader filtered out (`Entry::Hidden`): like
+    /// one outside the depth window it is not shown, but still ends and
+    /// times calls.
+    pub fn write_hidden<W: Write>(&mut self, w: &mut W, rec: &Record) -> io::Result<()> {
+        self.take_record(w, rec, false)
+    }
+
+    fn take_record<W: Write>(&mut self, w: &mut W, rec: &Record, shown: bool) -> io::Result<()> {
+        let mut rec = Cow::Borrowed(rec);
+        if let Some(filter) = &mut self.subtree {
+            match filter.select(&rec) {
+                Selection::Skip => return self.write_raw_lines(w),
+                Selection::Keep(r) => rec = Cow::Owned(r),
+                Selection::Enter(r, callers) => {
+                    // Two matches must not merge into one call at depth 0.
+                    self.write_raw_lines(w)?;
+                    self.end_calls(w)?;
+                    if shown && self.config.breadcrumb && !callers.is_empty() {
+                        self.fit_head(&r);
+                        self.write_breadcrumb(w, callers)?;
+                    }
+                    rec = Cow::Owned(r);
+                }
+            }
+        }
+
+        // Records outside the depth window, or hidden, still end (and
+        // time) calls.
+        let returns = if shown {
+            self.timer.push(&rec)
+        } else {
+            self.timer.push_hidden(&rec)
+        };
+        let depth = rec.depth;
+        if shown {
+            self.outside = !self.in_window(depth);
+        }
+        if !shown || self.outside {
+            self.write_raw_lines(w)?;
+            if !self.pruner.as_ref().is_some_and(Pruner::inside) {
+                self.write_returns(w, returns, None)?;
+            }
+            return Ok(());
+        }
+        if self.config.rebase {
+            let base = match self.config.min_depth {
+                Some(min) => min,
+                None => *self.base.insert(self.base.map_or(depth, |b| b.min(depth))),
+            };
+            if base > 0 {
+                rec.to_mut().depth -= base;
+            }
+        }
+        let rec: &Record = &rec;
+
+        let (show, ended) = match &mut self.pruner {
+            None => (true, None),
+            Some(pruner) => {
+                let pruning = pruner.push(rec);
+                (pruning.show, pruning.ended)
+            }
+        };
+        // Widen the head before the lines this record brings along.
+        if show {
+            self.fit_head(rec);
+        }
+        self.write_raw_lines(w)?;
+        let pruned_depth = ended.as_ref().map(|p| p.depth);
+        if let Some(p) = ended {
+            self.write_pruned(w, p)?;
+        }
+        if !show {
+            return Ok(());
+        }
+        self.write_returns(w, returns, pruned_depth)?;
+        let ts = rec
+            .ts
+            .as_deref()
+            .and_then(|t| self.config.timestamps.parse(t));
+        let delta = ts.zip(self.prev_ts).map(|(t, prev)| t.since(&prev));
+        if let Some(gap) = delta
+            && self.config.gap_threshold.is_some_and(|min| gap >= min)
+        {
+            self.write_gap(w, gap)?;
+        }
+        if self.config.day_separator
+            && let Some(t) = ts
+        {
+            let day = self.config.time_format.day(&t);
+            if self.day.is_some_and(|d| d != day) {
+                self.write_separator(w, &format_day(day))?;
+            }
+            self.day = Some(day);
+        }
+        let time = self.time_column(ts, delta);
+        if ts.is_some() {
+            self.first_ts = self.first_ts.or(ts);
+            self.prev_ts = ts;
+        }
+        self.depth = self.shown_depth(rec.depth);
+        if self.painter().is_none() {
+            return self.emit_record(w, rec, &time, &[]);
+        }
+        self.held
+            .push(self.depth, true, Held::Record(rec.clone(), time));
+        self.drain(w)
+    }
+
+    /// The time column of a record with timestamp `ts`, `delta` after the
+    /// previous one.
+    fn time_column(&self, ts: Option<Timestamp>, delta: Option<i128>) -> String {
+        let format = &self.config.time_format;
+        let Some(t) = ts else {
+            return format.placeholder();
+        };
+        let elapsed = match format.mode {
+            TimeMode::Absolute => return format.format(&t),
+            TimeMode::Relative => self.first_ts.map_or(0, |first| t.since(&first)),
+            TimeMode::Delta => delta.unwrap_or(0),
+            TimeMode::Call => self.timer.entry().map_or(0, |entry| t.since(&entry)),
+        };
+        let text = format.format_elapsed(elapsed);
+        if self.config.color {
+            color_heat(delta.unwrap_or(0), &text)
+        } else {
+            text
+        }
+    }
+
+    /// Write a line that is not a record (see `Malformed::Pass`) verbatim,
+    /// dimmed and indented to the depth of the previous record (one level
+    /// deeper with tree guides), its `|` in line with the records around it:
+    ///
+    /// ```text
+    /// 09:12:01.104 [T] lock.c:70 | <indent>unlock_object: unlock
+    ///              [~]           | <indent>text
+    /// ```
+    pub fn write_raw<W: Write>(&mut self, w: &mut W, text: &str) -> io::Result<()> {
+        if self.outside
+            || self.subtree.as_ref().is_some_and(|f| !f.inside())
+            || self.pruner.as_ref().is_some_and(Pruner::inside)
+        {
+            return Ok(());
+        }
+        if self.painter().is_none() {
+            self.raw.push((text.to_string(), self.depth));
+            return Ok(());
+        }
+        self.held
+            .push(self.depth, false, Held::Raw(text.to_string()));
+        self.drain(w)
+    }
+
+    /// Write the raw lines waiting for a record.
+    fn write_raw_lines<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        for (text, depth) in std::mem::take(&mut self.raw) {
+            self.emit_raw(w, &text, depth, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Write every held-back line, guessing undecided guides. For when
+    /// input stalls (`Entry::Idle`).
+    pub fn flush_pending<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.write_raw_lines(w)?;
+        while let Some((held, cont)) = self.held.pop_guess() {
+            self.emit(w, held, &cont)?;
+        }
+        Ok(())
+    }
+
+    /// Write every held-back line at the end of input, after the return
+    /// lines of the calls still open.
+    pub fn finish<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.write_raw_lines(w)?;
+        self.end_calls(w)?;
+        while let Some((held, cont)) = self.held.pop_final() {
+            self.emit(w, held, &cont)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a record at `depth` (before rebasing) is inside the
+    /// `min_depth`/`max_depth` window.
+    fn in_window(&self, depth: usize) -> bool {
+        let cfg = &self.config;
+        cfg.min_depth.is_none_or(|min| depth >= min) && cfg.max_depth.is_none_or(|max| depth <= max)
+    }
+
+    /// With `durations`, write the return lines of the shown calls in the
+    /// depth window among `returns`, at their rebased depth. Calls inside a pruned
+    /// one ending at `pruned_depth`, and the pruned call itself, return
+    /// silently.
+    fn write_returns<W: Write>(
+        &mut self,
+        w: &mut W,
+        returns: Vec<CallTiming>,
+        pruned_depth: Option<usize>,
+    ) -> io::Result<()> {
+        if !self.config.durations {
+            return Ok(());
+        }
+        for mut t in returns {
+            if !t.shown || !self.in_window(t.depth) {
+                continue;
+            }
+            if self.config.rebase {
+                t.depth -= self
+                    .config
+                    .min_depth
+                    .or(self.base)
+                    .unwrap_or(0)
+                    .min(t.depth);
+            }
+            if pruned_depth.is_none_or(|d| t.depth < d) {
+                self.write_return(w, t)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// How many levels a record at `depth` is indented, with `depth_cap`
+    /// and never more than [`MAX_DEPTH`].
+    fn shown_depth(&self, depth: usize) -> usize {
+        let cap = self.config.depth_cap.unwrap_or(MAX_DEPTH).min(MAX_DEPTH);
+        depth.min(cap)
+    }
+
+    /// Return from every open call: write the pending return lines.
+    fn end_calls<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        let pruned = self.pruner.as_mut().and_then(Pruner::finish);
+        let pruned_depth = pruned.as_ref().map(|p| p.depth);
+        if let Some(p) = pruned {
+            self.write_pruned(w, p)?;
+        }
+        let returns = self.timer.finish();
+        self.write_returns(w, returns, pruned_depth)
+    }
+
+    fn drain<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        while let Some((held, cont)) = self.held.pop_ready() {
+            self.emit(w, held, &cont)?;
+        }
+        Ok(())
+    }
+
+    fn emit<W: Write>(&mut self, w: &mut W, held: Held, cont: &[bool]) -> io::Result<()> {
+        match held {
+            Held::Record(rec, time) => self.emit_record(w, &rec, &time, cont),
+            Held::Return(t) => {
+                let depth = cont.len() - 1;
+                self.emit_return(w, &t, depth, cont)
+            }
+            Held::Raw(text) => {
+                let depth = cont.len() - 1;
+                self.emit_raw(w, &text, depth, cont)
+            }
+            Held::Breadcrumb(callers) => self.emit_breadcrumb(w, &callers),
+            Held::Separator(text) => self.emit_separator(w, &text),
+            Held::Gap(gap) => {
+                let depth = cont.len() - 1;
+                self.emit_gap(w, gap, depth, cont)
+            }
+            Held::Pruned(p) => {
+                let depth = cont.len() - 1;
+                self.emit_pruned(w, &p, depth, cont)
+            }
+        }
+    }
+
+    /// `cont` holds the guide continuation of levels `1..=depth + 1`; it is
+    /// empty without guides.
+    fn emit_record<W: Write>(
+        &mut self,
+        w: &mut W,
+        rec: &Record,
+        time: &str,
+        cont: &[bool],
+    ) -> io::Result<()> {
+        let level_ch = map_level(rec.level.as_deref().unwrap_or(""));
+
+        let func = rec.func.as_deref().unwrap_or("?");
+        let msg = rec.msg.as_deref().unwrap_or("");
+
+        let depth = self.shown_depth(rec.depth);
+        let painter = self.painter();
+        let (indent, trunks) = match painter {
+            Some(p) => (
+                p.record_prefix(depth, cont),
+                p.trunk_prefix(depth + 1, cont),
+            ),
+            None => {
+                let spaces = " ".repeat(depth.saturating_mul(self.config.indent_width));
+                (spaces.clone(), spaces)
+            }
+        };
+        // Beyond the cap the depth is spelled out instead.
+        let marker = if depth < rec.depth {
+            format!("[d={}] ", rec.depth)
+        } else {
+            String::new()
+        };
+
+        let lvl = if self.config.color {
+            color_level(level_ch)
+        } else {
+            level_ch.to_string()
+        };
+
+        let func_disp = if self.config.color && !marker.is_empty() {
+            format!("{DIM}{marker}{RESET}{}", color_func(func))
+        } else if self.config.color {
+            color_func(func)
+        } else {
+            format!("{marker}{func}")
+        };
+
+        let columns = self.columns(rec);
+        let extra = self.config.extra(rec);
+
+        let cfg = &self.config;
+        let mut head = String::new();
+        if cfg.show_time {
+            head.push_str(time);
+            head.push(' ');
+        }
+        if cfg.show_level {
+            head.push_str(&format!("[{lvl}] "));
+        }
+        head.push_str(&columns);
+        if cfg.show_location {
+            head.push_str(&location(rec));
+            head.push(' ');
+        }
+        let width = visible_width(&head);
+        self.head_width = self.head_width.max(width);
+        head.extend(std::iter::repeat_n(' ', self.head_width - width));
+
+        let mut lines = msg.lines();
+        let first = lines.next().unwrap_or("");
+        let mut rest = lines.peekable();
+        if rest.peek().is_none() {
+            return writeln!(w, "{head}| {indent}{func_disp}: {first}{extra}");
+        }
+
+        if cfg.multiline == Multiline::Collapse {
+            let marker = if cfg.color {
+                format!("{DIM}⏎{RESET}")
+            } else {
+                "⏎".to_string()
+            };
+            let joined = msg.lines().collect::<Vec<_>>().join(&marker);
+            return writeln!(w, "{head}| {indent}{func_disp}: {joined}{extra}");
+        }
+
+        // Hanging indent: keep the `|` column, then line up under the first
+        // character of the message.
+        writeln!(w, "{head}| {indent}{func_disp}: {first}")?;
+        let head = self.head_width;
+        // With guides `trunks` already covers the first column under the
+        // function name (the trunk to its children).
+        let hang_width = marker.chars().count() + func.chars().count() + 2;
+        let hang_width = match painter {
+            Some(_) => hang_width.saturating_sub(self.config.indent_width),
+            None => hang_width,
+        };
+        let hang = " ".repeat(hang_width);
+        while let Some(line) = rest.next() {
+            let tail = if rest.peek().is_none() {
+                extra.as_str()
+            } else {
+                ""
+            };
+            writeln!(w, "{:head$}| {trunks}{hang}{line}{tail}", "")?;
+        }
+        Ok(())
+    }
+
+    /// Queue or write the return line of a finished call. Calls with a
+    /// single record or without timestamps have nothing to report.
+    fn write_return<W: Write>(&mut self, w: &mut W, t: CallTiming) -> io::Result<()> {
+        if t.records <= 1 || t.inclusive.is_none() {
+            return Ok(());
+        }
+        if self.painter().is_none() {
+            return self.emit_return(w, &t, self.shown_depth(t.depth), &[]);
+        }
+        self.held
+            .push(self.shown_depth(t.depth), false, Held::Return(t));
+        self.drain(w)
+    }
+
+    /// Inside the call that returns, like a raw line:
+    ///
+    /// ```text
+    ///              [<]           | <indent>← func 12.4ms (self 3.1ms)
+    /// ```
+    fn emit_return<W: Write>(
+        &mut self,
+        w: &mut W,
+        t: &CallTiming,
+        depth: usize,
+        cont: &[bool],
+    ) -> io::Result<()> {
+        let prefix = self.synthetic_prefix('<');
+        let indent = self.synthetic_indent(depth, cont);
+        let func = t.func.as_deref().unwrap_or("?");
+        let inclusive = t.inclusive.unwrap_or(0);
+        let mut took = format_duration(inclusive);
+        if t.has_children
+            && let Some(excl) = t.exclusive
+        {
+            took.push_str(&format!(" (self {})", format_duration(excl)));
+        }
+        let slow = self
+            .config
+            .slow_threshold
+            .is_some_and(|min| inclusive >= min);
+        match (self.config.color, slow) {
+            (true, true) => writeln!(
+                w,
+                "{prefix}{indent}{DIM}← {func}{RESET} {BOLD}{RED}{took}{RESET}",
+            ),
+            (true, false) => writeln!(w, "{prefix}{indent}{DIM}← {func} {took}{RESET}"),
+            (false, true) => writeln!(w, "{prefix}{indent}← {func} {took} [slow]"),
+            (false, false) => writeln!(w, "{prefix}{indent}← {func} {took}"),
+        }
+    }
+
+    /// Queue or write the summary of a pruned call; nothing when it had
+    /// nothing to hide.
+    fn write_pruned<W: Write>(&mut self, w: &mut W, p: Pruned) -> io::Result<()> {
+        if p.hidden == 0 {
+            return Ok(());
+        }
+        if self.painter().is_none() {
+            return self.emit_pruned(w, &p, self.shown_depth(p.depth), &[]);
+        }
+        self.held
+            .push(self.shown_depth(p.depth), false, Held::Pruned(p));
+        self.drain(w)
+    }
+
+    /// At the depth of the pruned call, like its return line:
+    ///
+    /// ```text
+    ///              [-]           | <indent>… 42 records pruned (3.1ms)
+    /// ```
+    fn emit_pruned<W: Write>(
+        &mut self,
+        w: &mut W,
+        p: &Pruned,
+        depth: usize,
+        cont: &[bool],
+    ) -> io::Result<()> {
+        let prefix = self.synthetic_prefix('-');
+        let indent = self.synthetic_indent(depth, cont);
+        let plural = if p.hidden == 1 { "" } else { "s" };
+        let mut text = format!("… {} record{plural} pruned", p.hidden);
+        if let Some(elapsed) = p.elapsed {
+            text.push_str(&format!(" ({})", format_duration(elapsed)));
+        }
+        if self.config.color {
+            writeln!(w, "{prefix}{indent}{DIM}{text}{RESET}")
+        } else {
+            writeln!(w, "{prefix}{indent}{text}")
+        }
+    }
+
+    /// Write (or queue, after what is held back) a heading line such as a
+    /// new date or the start of a thread's records.
+    pub fn write_separator<W: Write>(&mut self, w: &mut W, text: &str) -> io::Result<()> {
+        if self.painter().is_none() {
+            return self.emit_separator(w, text);
+        }
+        self.held.push(0, false, Held::Separator(text.to_string()));
+        self.drain(w)
+    }
+
+    /// At the left margin, e.g. before the first record of a new date (in
+    /// the display zone):
+    ///
+    /// ```text
+    ///              [=]           | ── 2025-02-16 ──
+    /// ```
+    fn emit_separator<W: Write>(&mut self, w: &mut W, text: &str) -> io::Result<()> {
+        let prefix = self.synthetic_prefix('=');
+        if self.config.color {
+            writeln!(w, "{prefix}{BOLD}── {text} ──{RESET}")
+        } else {
+            writeln!(w, "{prefix}── {text} ──")
+        }
+    }
+
+    /// Queue or write the line marking a long pause before a record.
+    fn write_gap<W: Write>(&mut self, w: &mut W, gap: i128) -> io::Result<()> {
+        if self.painter().is_none() {
+            return self.emit_gap(w, gap, self.depth, &[]);
+        }
+        self.held.push(self.depth, false, Held::Gap(gap));
+        self.drain(w)
+    }
+
+    /// At the depth of the record before the pause, like a raw line:
+    ///
+    /// ```text
+    ///              [:]           | <indent>⋮ 1.250s gap
+    /// ```
+    fn emit_gap<W: Write>(
+        &mut self,
+        w: &mut W,
+        gap: i128,
+        depth: usize,
+        cont: &[bool],
+    ) -> io::Result<()> {
+        let prefix = self.synthetic_prefix(':');
+        let indent = self.synthetic_indent(depth, cont);
+        let gap = format_duration(gap);
+        if self.config.color {
+            writeln!(w, "{prefix}{indent}{YELLOW}⋮ {gap} gap{RESET}")
+        } else {
+            writeln!(w, "{prefix}{indent}⋮ {gap} gap")
+        }
+    }
+
+    /// Padding for lines without a time, as wide as the time column.
+    fn blank(&self) -> String {
+        " ".repeat(self.time_width)
+    }
+
+    /// The start of a line that is not a record, up to the `| `: a blank
+    /// time column and `[tag]` in place of the level, padded to the head
+    /// width so the `|` stays in one column.
+    fn synthetic_prefix(&self, tag: char) -> String {
+        let mut head = String::new();
+        if self.config.show_time {
+            head.push_str(&self.blank());
+            head.push(' ');
+        }
+        head.push_str(&format!("[{tag}] "));
+        let width = self.head_width.max(head.chars().count());
+        format!("{head:width$}| ")
+    }
+
+    /// Indentation of a line that is not a record, at `depth`: the same as
+    /// a record there, but only the trunks with guides.
+    fn synthetic_indent(&self, depth: usize, cont: &[bool]) -> String {
+        match self.painter() {
+            Some(p) => p.trunk_prefix(depth + 1, cont),
+            None => " ".repeat(depth.saturating_mul(self.config.indent_width)),
+        }
+    }
+
+    /// Queue or write the callers of a subtree match.
+    fn write_breadcrumb<W: Write>(&mut self, w: &mut W, callers: Vec<String>) -> io::Result<()> {
+        if self.painter().is_none() {
+            return self.emit_breadcrumb(w, &callers);
+        }
+        self.held.push(0, false, Held::Breadcrumb(callers));
+        self.drain(w)
+    }
+
+    /// Above a subtree match:
+    ///
+    /// ```text
+    ///              [>]           | main › run_query ›
+    /// ```
+    fn emit_breadcrumb<W: Write>(&mut self, w: &mut W, callers: &[String]) -> io::Result<()> {
+        let prefix = self.synthetic_prefix('>');
+        let path = callers.join(" › ");
+        if self.config.color {
+            writeln!(w, "{prefix}{DIM}{path} ›{RESET}")
+        } else {
+            writeln!(w, "{prefix}{path} ›")
+        }
+    }
+
+    fn emit_raw<W: Write>(
+        &mut self,
+        w: &mut W,
+        text: &str,
+        depth: usize,
+        cont: &[bool],
+    ) -> io::Result<()> {
+        let prefix = self.synthetic_prefix('~');
+        let indent = self.synthetic_indent(depth, cont);
+        if self.config.color {
+            writeln!(w, "{prefix}{indent}{DIM}{text}{RESET}")
+        } else {
+            writeln!(w, "{prefix}{indent}{text}")
+        }
+    }
+
+    /// Grow the head width to fit the head of `rec`, which is about to be
+    /// shown.
+    fn fit_head(&mut self, rec: &Record) {
+        let mut width = visible_width(&self.columns(rec));
+        let cfg = &self.config;
+        if cfg.show_time {
+            width += self.time_width + 1;
+        }
+        if cfg.show_level {
+            width += "[L] ".len();
+        }
+        if cfg.show_location {
+            width += visible_width(&location(rec)) + 1;
+        }
+        self.head_width = self.head_width.max(width);
+    }
+
+    /// The promoted columns, each padded and followed by a space.
+    fn columns(&mut self, rec: &Record) -> String {
+        let mut out = String::new();
+        for (key, width) in self.config.columns.iter().zip(&mut self.column_widths) {
+            let mut cell = String::new();
+            if let Some(val) = rec.extra(key) {
+                push_pair(&mut cell, key, val);
+            }
+            let len = cell.chars().count();
+            *width = (*width).max(len);
+            out.push_str(&cell);
+            out.extend(std::iter::repeat_n(' ', *width - len + 1));
+        }
+        out
+    }
+
+    /// Convenience wrapper around [`Renderer::write_record`]; with tree
+    /// guides the result may be empty or hold earlier records.
+    pub fn render_to_string(&mut self, rec: &Record) -> String {
+        let mut buf = Vec::new();
+        self.write_record(&mut buf, rec)
+            .expect("writing to a Vec cannot fail");
+        String::from_utf8(buf).expect("renderer emits UTF-8")
+    }
+}
+
+/// `file:line`, with `?` for what is missing.
+fn location(rec: &Record) -> String {
+    let file = rec.file.as_deref().unwrap_or("?");
+    let line = rec.line.as_deref().unwrap_or("?");
+    format!("{file}:{line}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(config: RenderConfig, input: &str) -> String {
+        let mut renderer = Renderer::new(config);
+        let mut out = Vec::new();
+        for line in input.lines() {
+            match Record::parse(line) {
+                Ok(rec) => renderer.write_record(&mut out, &rec).unwrap(),
+                Err(_) => renderer.write_raw(&mut out, line).unwrap(),
+            }
+        }
+        renderer.finish(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Column of the `|` on each line.
+    fn bars(output: &str) -> Vec<usize> {
+        output
+            .lines()
+            .map(|l| l.chars().position(|c| c == '|').unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hidden_records_still_time_calls() {
+        let lines = [
+            ("ts=2025-02-15T09:12:01.000Z depth=0 func=main msg=a", true),
+            ("ts=2025-02-15T09:12:01.100Z depth=1 func=f msg=b", false),
+            ("ts=2025-02-15T09:12:01.300Z depth=2 func=g msg=c", false),
+            ("ts=2025-02-15T09:12:01.400Z depth=0 func=main msg=d", true),
+        ];
+        let mut renderer = Renderer::new(RenderConfig {
+            durations: true,
+            show_time: false,
+            show_level: false,
+            show_location: false,
+            ..RenderConfig::default()
+        });
+        let mut out = Vec::new();
+        for (line, shown) in lines {
+            let rec = Record::parse(line).unwrap();
+            if shown {
+                renderer.write_record(&mut out, &rec).unwrap();
+            } else {
+                renderer.write_hidden(&mut out, &rec).unwrap();
+            }
+        }
+        renderer.finish(&mut out).unwrap();
+        // `f` and `g` return silently, but their time is not `main`'s own.
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "| main: a\n| main: d\n[<] | ← main 400.0ms (self 200.0ms)\n"
+        );
+    }
+
+    #[test]
+    fn records_outside_the_depth_window_still_time_calls() {
+        let input = "ts=2025-02-15T09:12:01.000Z depth=0 func=main msg=a\n\
+                     ts=2025-02-15T09:12:01.100Z depth=1 func=f msg=b\n\
+                     ts=2025-02-15T09:12:01.300Z depth=2 func=g msg=c\n\
+                     ts=2025-02-15T09:12:01.400Z depth=0 func=main msg=d";
+        let config = RenderConfig {
+            durations: true,
+            max_depth: Some(1),
+            show_time: false,
+            show_level: false,
+            show_location: false,
+            ..RenderConfig::default()
+        };
+        let out = render(config, input);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[2], "[<] |     ← f 200.0ms (self 200.0ms)");
+        assert!(!out.contains("g: c"));
+    }
+
+    #[test]
+    fn synthetic_lines_line_up_with_the_record_after() {
+        // Each case: a narrow record, something that writes a synthetic
+        // line, a wider record, and a narrow one again. The `|` only moves
+        // once, before the synthetic line.
+        let rec = |time: &str, depth: usize, file: &str, func: &str| {
+            format!(
+                "ts=2025-02-{time}Z level=info depth={depth} file={file} line=1 func={func} msg=m"
+            )
+        };
+        let cases: Vec<(RenderConfig, Vec<String>, &str)> = vec![
+            (
+                RenderConfig::default(),
+                vec![
+                    rec("15T09:12:01.100", 0, "a.c", "main"),
+                    "stray output".to_string(),
+                    rec("15T09:12:01.200", 1, "btree.c", "f"),
+                ],
+                "             [~]           | stray output",
+            ),
+            (
+                RenderConfig {
+                    durations: true,
+                    ..RenderConfig::default()
+                },
+                vec![
+                    rec("15T09:12:01.100", 0, "a.c", "main"),
+                    rec("15T09:12:01.200", 1, "a.c", "f"),
+                    rec("15T09:12:01.250", 1, "a.c", "f"),
+                    rec("15T09:12:01.300", 0, "btree.c", "main"),
+                ],
+                "             [<]           |     ← f 50.0ms",
+            ),
+            (
+                RenderConfig {
+                    prune: vec![Glob::new("lock")],
+                    ..RenderConfig::default()
+                },
+                vec![
+                    rec("15T09:12:01.100", 1, "a.c", "lock"),
+                    rec("15T09:12:01.200", 2, "a.c", "wait"),
+                    rec("15T09:12:01.300", 1, "btree.c", "unlock"),
+                ],
+                "             [-]           |     … 1 record pruned (100.0ms)",
+            ),
+            (
+                RenderConfig {
+                    day_separator: true,
+                    ..RenderConfig::default()
+                },
+                vec![
+                    rec("15T23:59:59.900", 0, "a.c", "main"),
+                    rec("16T00:00:00.100", 0, "btree.c", "main"),
+                ],
+                "             [=]           | ── 2025-02-16 ──",
+            ),
+            (
+                RenderConfig {
+                    gap_threshold: Some(1_000_000_000),
+                    ..RenderConfig::default()
+                },
+                vec![
+                    rec("15T09:12:01.100", 1, "a.c", "f"),
+                    rec("15T09:12:03.100", 1, "btree.c", "f"),
+                ],
+                "             [:]           |     ⋮ 2.000s gap",
+            ),
+            (
+                RenderConfig {
+                    subtree: Some(Glob::new("f")),
+                    breadcrumb: true,
+                    ..RenderConfig::default()
+                },
+                vec![
+                    rec("15T09:12:01.100", 0, "a.c", "main"),
+                    rec("15T09:12:01.200", 1, "a.c", "run"),
+                    rec("15T09:12:01.300", 2, "btree.c", "f"),
+                ],
+                "             [>]           | main › run ›",
+            ),
+        ];
+        for (config, mut input, line) in cases {
+            input.push(rec("15T09:12:04.000", 0, "a.c", "main"));
+            let out = render(config, &input.join("\n"));
+            let at = out.lines().position(|l| l == line);
+            assert!(at.is_some(), "no `{line}` in\n{out}");
+            let bars = bars(&out);
+            assert!(bars[at.unwrap()..].iter().all(|&b| b == 27), "{out}");
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// ---------- summary statistics ----------
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use crate::level::map_level;
+use crate::record::Record;
+
+/// Running counters for `depthlog stats`.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub records: usize,
+    /// Lines that did not parse as logfmt.
+    pub malformed: usize,
+    pub max_depth: usize,
+    pub by_level: BTreeMap<char, usize>,
+    pub by_func: HashMap<String, usize>,
+    pub by_file: HashMap<String, usize>,
+    pub first_ts: Option<String>,
+    pub last_ts: Option<String>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, rec: &Record) {
+        self.records += 1;
+        self.max_depth = self.max_depth.max(rec.depth);
+        let lvl = map_level(rec.level.as_deref().unwrap_or(""));
+        *self.by_level.entry(lvl).or_default() += 1;
+        if let Some(func) = &rec.func {
+            *self.by_func.entry(func.clone()).or_default() += 1;
+        }
+        if let Some(file) = &rec.file {
+            *self.by_file.entry(file.clone()).or_default() += 1;
+        }
+        if let Some(ts) = &rec.ts {
+            if self.first_ts.is_none() {
+                self.first_ts = Some(ts.clone());
+            }
+            self.last_ts = Some(ts.clone());
+        }
+    }
+
+    /// Write a human-readable report listing at most `top` functions/files.
+    pub fn write_report<W: Write>(&self, w: &mut W, top: usize) -> io::Result<()> {
+        writeln!(w, "records:   {}", self.records)?;
+        writeln!(w, "malformed: {}", self.malformed)?;
+        writeln!(w, "max depth: {}", self.max_depth)?;
+        if let (Some(first), Some(last)) = (&self.first_ts, &self.last_ts) {
+            writeln!(w, "first ts:  {first}")?;
+            writeln!(w, "last ts:   {last}")?;
+        }
+        writeln!(w, "levels:")?;
+        for (lvl, n) in &self.by_level {
+            writeln!(w, "  [{lvl}] {n:>10}")?;
+        }
+        write_top(w, "functions", &self.by_func, top)?;
+        write_top(w, "files", &self.by_file, top)
+    }
+}
+
+fn write_top<W: Write>(
+    w: &mut W,
+    title: &str,
+    counts: &HashMap<String, usize>,
+    top: usize,
+) -> io::Result<()> {
+    let mut sorted: Vec<_> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    writeln!(w, "{title} (top {}):", top.min(sorted.len()))?;
+    for (name, n) in sorted.into_iter().take(top) {
+        writeln!(w, "  {n:>10}  {name}")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,843 @@
+// ---------- line-by-line streaming ----------
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::lanes::Lanes;
+use crate::level::LevelFilter;
+use crate::logfmt::DuplicatePolicy;
+use crate::record::Record;
+use crate::render::Renderer;
+use crate::threads::Threads;
+use crate::time::{TimeBound, Timestamp, TimestampParser};
+
+const READ_BUF_SIZE: usize = 64 * 1024;
+const WRITE_BUF_SIZE: usize = 64 * 1024;
+
+/// What to do with a line that is not valid logfmt (a panic message, a
+/// stack trace, stray stderr output).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Malformed {
+    /// Drop it silently.
+    #[default]
+    Skip,
+    /// Hand it to the consumer as [`Entry::Raw`] so it can be shown as is.
+    Pass,
+    /// Drop it, but print the line number, reason and text to stderr.
+    Report,
+    /// Stop with an `InvalidData` error naming the line.
+    Strict,
+}
+
+impl std::str::FromStr for Malformed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Malformed::Skip),
+            "pass" => Ok(Malformed::Pass),
+            "report" => Ok(Malformed::Report),
+            "strict" => Ok(Malformed::Strict),
+            other => Err(format!(
+                "expected skip, pass, report or strict, got `{other}`"
+            )),
+        }
+    }
+}
+
+/// How input lines become records.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    /// How repeated keys on one line are resolved.
+    pub duplicates: DuplicatePolicy,
+    /// What happens to lines that do not parse.
+    pub malformed: Malformed,
+    /// Records below their level threshold are dropped.
+    pub levels: LevelFilter,
+    /// Records before this are dropped.
+    pub since: Option<TimeBound>,
+    /// Records after this are dropped; reading stops at the first one as
+    /// long as timestamps have not gone backwards so far.
+    pub until: Option<TimeBound>,
+    /// How `ts` values are read. Consumers of the stream should read them
+    /// with clones of this parser, so they agree on numeric timestamps.
+    pub timestamps: TimestampParser,
+    /// Hand the records the level and time filters drop over as
+    /// [`Entry::Hidden`] instead of leaving them out.
+    pub hidden: bool,
+}
+
+/// What [`TimeWindow::check`] decided.
+enum Verdict {
+    Keep,
+    Drop,
+    /// Past `until` in time-ordered input: nothing more can match.
+    Stop,
+}
+
+/// `since` / `until` applied to a stream. Records without a timestamp, and
+/// raw lines, go with the record before them.
+struct TimeWindow {
+    timestamps: TimestampParser,
+    since: Option<TimeBound>,
+    until: Option<TimeBound>,
+    /// The bounds, once resolved against the first timestamp.
+    resolved: Option<(Option<Timestamp>, Option<Timestamp>)>,
+    last: Option<Timestamp>,
+    ordered: bool,
+    inside: bool,
+}
+
+impl TimeWindow {
+    fn new(opts: &ReadOptions, timestamps: &TimestampParser) -> Option<Self> {
+        if opts.since.is_none() && opts.until.is_none() {
+            return None;
+        }
+        Some(TimeWindow {
+            timestamps: timestamps.clone(),
+            since: opts.since,
+            until: opts.until,
+            resolved: None,
+            last: None,
+            ordered: true,
+            inside: opts.since.is_none(),
+        })
+    }
+
+    fn check(&mut self, rec: &Record) -> Verdict {
+        let Some(ts) = rec.ts.as_deref().and_then(|t| self.timestamps.parse(t)) else {
+            return if self.inside {
+                Verdict::Keep
+            } else {
+                Verdict::Drop
+            };
+        };
+        let (since, until) = *self.resolved.get_or_insert_with(|| {
+            (
+                self.since.map(|b| b.resolve(&ts)),
+                self.until.map(|b| b.resolve(&ts)),
+            )
+        });
+        if self.last.is_some_and(|last| ts.nanos < last.nanos) {
+            self.ordered = false;
+        }
+        self.last = Some(ts);
+
+        self.inside = false;
+        if until.is_some_and(|u| ts.nanos > u.nanos) {
+            return if self.ordered {
+                Verdict::Stop
+            } else {
+                Verdict::Drop
+            };
+        }
+        if since.is_some_and(|s| ts.nanos < s.nanos) {
+            return Verdict::Drop;
+        }
+        self.inside = true;
+        Verdict::Keep
+    }
+}
+
+/// What [`for_each_entry`] hands to its consumer.
+#[derive(Debug, Clone, Copy)]
+pub enum Entry<'a> {
+    Record(&'a Record),
+    /// A line that did not parse, with surrounding whitespace trimmed. Only
+    /// produced under [`Malformed::Pass`].
+    Raw(&'a str),
+    /// A record the level or time filters drop, for consumers that track
+    /// calls through it. Only produced with [`ReadOptions::hidden`].
+    Hidden(&'a Record),
+    /// No input arrived for [`IDLE_AFTER`]. Anything held back waiting for
+    /// later lines (e.g. tree guides) should be written now.
+    Idle,
+}
+
+/// How long input must be quiet before consumers see [`Entry::Idle`].
+pub const IDLE_AFTER: Duration = Duration::from_millis(100);
+
+/// Feed every entry of `input` to `f` together with a buffered `output`.
+///
+/// Input is read on a background thread, so the consumer can tell a slow
+/// producer (`tail -f log | ...`) from a large file: output is flushed as
+/// soon as no more input is waiting, and [`Entry::Idle`] follows once input
+/// has been quiet for [`IDLE_AFTER`]. Bulk input is still written in large
+/// chunks. The thread is detached and ends with the process, hence
+/// `R: 'static`.
+///
+/// Lines are decoded as UTF-8, lossily; lines that do not parse are handled
+/// according to `opts.malformed`. Write errors, including a closed pipe, are
+/// returned to the caller; see [`is_broken_pipe`].
+pub fn for_each_entry<R, W, F>(input: R, output: W, opts: &ReadOptions, mut f: F) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+    F: FnMut(Entry<'_>, &mut BufWriter<W>) -> io::Result<()>,
+{
+    let mut out = BufWriter::with_capacity(WRITE_BUF_SIZE, output);
+    let batches = spawn_reader(input);
+    let mut decoder = Decoder::new(opts, None, &opts.timestamps);
+
+    'read: loop {
+        let batch = match batches.try_recv() {
+            Ok(batch) => batch,
+            Err(TryRecvError::Disconnected) => break,
+            Err(TryRecvError::Empty) => {
+                // Input is idle (or about to be): make what we have visible.
+                out.flush()?;
+                match batches.recv_timeout(IDLE_AFTER) {
+                    Ok(batch) => batch,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                    Err(RecvTimeoutError::Timeout) => {
+                        f(Entry::Idle, &mut out)?;
+                        out.flush()?;
+                        match batches.recv() {
+                            Ok(batch) => batch,
+                            Err(_) => break,
+                        }
+                    }
+                }
+            }
+        };
+
+        for buf in batch? {
+            let decoded = decoder.decode(&buf);
+            match decoded {
+                Ok(Decoded::Record(rec)) => f(Entry::Record(&rec), &mut out)?,
+                Ok(Decoded::Hidden(rec)) => f(Entry::Hidden(&rec), &mut out)?,
+                Ok(Decoded::Raw(text)) => f(Entry::Raw(&text), &mut out)?,
+                Ok(Decoded::Nothing) => {}
+                Ok(Decoded::Stop) => break 'read,
+                Err(err) => {
+                    out.flush()?;
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    out.flush()
+}
+
+/// One input of [`for_each_merged_entry`].
+pub struct MergeInput<R> {
+    /// Names the input in reports and errors.
+    pub name: String,
+    pub input: R,
+    /// Reads the timestamps of this input (see [`ReadOptions::timestamps`],
+    /// which is not used for merged inputs).
+    pub timestamps: TimestampParser,
+}
+
+/// How many undated lines at the start of an input wait for its first
+/// timestamp before they are handed over on their own.
+const UNDATED_LEAD: usize = 1024;
+
+/// How many received lines an input may have waiting while the merge waits
+/// for another input; beyond this it goes on without the one it waits for.
+const MERGE_BACKLOG: usize = 16 * 1024;
+
+/// One input of [`for_each_merged_entry`], with the state of its reader.
+struct MergeSource<'a> {
+    /// Lines received but not decoded yet.
+    lines: VecDeque<Vec<u8>>,
+    decoder: Decoder<'a>,
+    /// Decoded entries, with the time they sort by.
+    ready: VecDeque<(Option<Timestamp>, Decoded)>,
+    /// Entries before the first timestamp of the input, which they will
+    /// sort by.
+    undated: Vec<Decoded>,
+    /// The time of the last record, which undated entries sort by.
+    last: Option<Timestamp>,
+    /// No more lines will come: the reader is finished or `until` passed.
+    done: bool,
+    /// When lines last arrived (or the merge started).
+    heard: Instant,
+}
+
+impl MergeSource<'_> {
+    /// Decode received lines until an entry is ready or none are left.
+    fn fill(&mut self) -> io::Result<()> {
+        while self.ready.is_empty() {
+            let Some(buf) = self.lines.pop_front() else {
+                if self.done {
+                    self.release_undated(None);
+                }
+                return Ok(());
+            };
+            let decoded = self.decoder.decode(&buf)?;
+            let entry = match decoded {
+                Decoded::Nothing => continue,
+                Decoded::Stop => {
+                    self.done = true;
+                    self.lines.clear();
+                    continue;
+                }
+                Decoded::Record(ref rec) | Decoded::Hidden(ref rec) => {
+                    if let Some(ts) = self.decoder.timestamp(rec) {
+                        self.last = Some(ts);
+                        self.release_undated(Some(ts));
+                    }
+                    decoded
+                }
+                raw => raw,
+            };
+            if self.last.is_some() {
+                self.ready.push_back((self.last, entry));
+            } else {
+                self.undated.push(entry);
+                if self.undated.len() >= UNDATED_LEAD {
+                    self.release_undated(None);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn release_undated(&mut self, at: Option<Timestamp>) {
+        self.ready
+            .extend(self.undated.drain(..).map(|entry| (at, entry)));
+    }
+
+    /// Whether the next entry of this input is still to come.
+    fn waiting(&self) -> bool {
+        self.ready.is_empty() && !(self.done && self.lines.is_empty() && self.undated.is_empty())
+    }
+
+    /// How much longer the merge waits for this input, if at all: up to
+    /// [`IDLE_AFTER`] after it was last heard from.
+    fn patience(&self) -> Option<Duration> {
+        let left = IDLE_AFTER.checked_sub(self.heard.elapsed())?;
+        (self.waiting() && !left.is_zero()).then_some(left)
+    }
+}
+
+/// Like [`for_each_entry`] for several inputs at once, each with its own
+/// reader thread: records are handed over in timestamp order (a k-way
+/// merge of inputs that are each in order), together with the index of
+/// their input. Records without a timestamp and raw lines keep their place
+/// after the record before them in the same input, and those before the
+/// first timestamp of an input go just before it; ties go to the earlier
+/// input.
+///
+/// The merge waits for every input to show its next entry, but for one
+/// that has sent nothing for [`IDLE_AFTER`] it goes on without it, as it
+/// does when another input has [`MERGE_BACKLOG`] lines waiting. Once no
+/// input has sent anything for [`IDLE_AFTER`], [`Entry::Idle`] is delivered
+/// (with index 0).
+pub fn for_each_merged_entry<R, W, F>(
+    inputs: Vec<MergeInput<R>>,
+    output: W,
+    opts: &ReadOptions,
+    mut f: F,
+) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+    F: FnMut(usize, Entry<'_>, &mut BufWriter<W>) -> io::Result<()>,
+{
+    let mut out = BufWriter::with_capacity(WRITE_BUF_SIZE, output);
+    // One channel for all readers: batches tagged with their input, `None`
+    // when an input ends.
+    let (tx, rx) = mpsc::sync_channel::<(usize, Option<Batch>)>(64);
+    let mut names = Vec::new();
+    let mut parsers = Vec::new();
+    for (i, input) in inputs.into_iter().enumerate() {
+        let tx = tx.clone();
+        let reader = input.input;
+        thread::spawn(move || {
+            read_batches(reader, |batch| tx.send((i, Some(batch))).is_ok());
+            let _ = tx.send((i, None));
+        });
+        names.push(input.name);
+        parsers.push(input.timestamps);
+    }
+    drop(tx);
+    let mut sources: Vec<MergeSource> = names
+        .iter()
+        .zip(&parsers)
+        .map(|(name, timestamps)| MergeSource {
+            lines: VecDeque::new(),
+            decoder: Decoder::new(opts, Some(name), timestamps),
+            ready: VecDeque::new(),
+            undated: Vec::new(),
+            last: None,
+            done: false,
+            heard: Instant::now(),
+        })
+        .collect();
+
+    let deliver = |sources: &mut [MergeSource], (i, batch): (usize, Option<Batch>)| {
+        let source = &mut sources[i];
+        source.heard = Instant::now();
+        match batch {
+            Some(batch) if !source.done => source.lines.extend(batch?),
+            Some(_) => {}
+            None => source.done = true,
+        }
+        io::Result::Ok(())
+    };
+    // Whether `Entry::Idle` was delivered since input last arrived.
+    let mut idle = false;
+    let result = loop {
+        let received = rx.try_iter().try_for_each(|msg| {
+            idle = false;
+            deliver(&mut sources, msg)
+        });
+        if let Err(err) = received.and_then(|()| sources.iter_mut().try_for_each(MergeSource::fill))
+        {
+            break Err(err);
+        }
+        let patience = sources.iter().filter_map(MergeSource::patience).min();
+        let full = sources.iter().any(|s| s.lines.len() >= MERGE_BACKLOG);
+        if patience.is_none() || full {
+            let next = sources
+                .iter()
+                .enumerate()
+                .filter_map(|(i, s)| Some((s.ready.front()?.0, i)))
+                .min();
+            if let Some((_, i)) = next {
+                match sources[i].ready.pop_front() {
+                    Some((_, Decoded::Record(rec))) => f(i, Entry::Record(&rec), &mut out)?,
+                    Some((_, Decoded::Hidden(rec))) => f(i, Entry::Hidden(&rec), &mut out)?,
+                    Some((_, Decoded::Raw(text))) => f(i, Entry::Raw(&text), &mut out)?,
+                    _ => {}
+                }
+                continue;
+            }
+            if !sources.iter().any(MergeSource::waiting) {
+                break Ok(());
+            }
+        }
+        out.flush()?;
+        // Wake up when the merge stops waiting for an input, or when all of
+        // them have been quiet for `IDLE_AFTER`.
+        let quiet_in = sources
+            .iter()
+            .map(|s| IDLE_AFTER.saturating_sub(s.heard.elapsed()))
+            .max()
+            .filter(|_| !idle);
+        match rx.recv_timeout(patience.or(quiet_in).unwrap_or(IDLE_AFTER)) {
+            Ok(msg) => {
+                idle = false;
+                if let Err(err) = deliver(&mut sources, msg) {
+                    break Err(err);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                let quiet = sources.iter().all(|s| s.heard.elapsed() >= IDLE_AFTER);
+                if quiet && !idle {
+                    f(0, Entry::Idle, &mut out)?;
+                    out.flush()?;
+                    idle = true;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                for source in &mut sources {
+                    source.done = true;
+                }
+            }
+        }
+    };
+    out.flush()?;
+    result
+}
+
+/// What [`Decoder::decode`] made of a line.
+enum Decoded {
+    Record(Record),
+    /// Dropped by a filter, under [`ReadOptions::hidden`].
+    Hidden(Record),
+    Raw(String),
+    /// Blank, filtered out or dropped as malformed.
+    Nothing,
+    /// Past `until`; see [`Verdict::Stop`].
+    Stop,
+}
+
+/// Turns the lines of one input into entries: parsing, `opts.malformed`,
+/// and the level and time filters.
+struct Decoder<'a> {
+    opts: &'a ReadOptions,
+    /// Names the input in reports and errors, when there are several.
+    name: Option<&'a str>,
+    timestamps: TimestampParser,
+    line_no: usize,
+    filter_levels: bool,
+    window: Option<TimeWindow>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(opts: &'a ReadOptions, name: Option<&'a str>, timestamps: &TimestampParser) -> Self {
+        Decoder {
+            opts,
+            name,
+            timestamps: timestamps.clone(),
+            line_no: 0,
+            filter_levels: !opts.levels.is_everything(),
+            window: TimeWindow::new(opts, timestamps),
+        }
+    }
+
+    fn timestamp(&self, rec: &Record) -> Option<Timestamp> {
+        self.timestamps.parse(rec.ts.as_deref()?)
+    }
+
+    fn decode(&mut self, buf: &[u8]) -> io::Result<Decoded> {
+        self.line_no += 1;
+        // Invalid UTF-8 (a truncated write, a binary blob) must not cost us
+        // the whole line: decode lossily, bad bytes become U+FFFD.
+        let line = String::from_utf8_lossy(buf);
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Ok(Decoded::Nothing);
+        }
+        let opts = self.opts;
+        let decoded = match Record::parse_with(trimmed, opts.duplicates) {
+            Ok(rec) => match self
+                .window
+                .as_mut()
+                .map_or(Verdict::Keep, |w| w.check(&rec))
+            {
+                Verdict::Stop => Decoded::Stop,
+                Verdict::Keep if !self.filter_levels || opts.levels.enabled(&rec) => {
+                    Decoded::Record(rec)
+                }
+                Verdict::Keep | Verdict::Drop if opts.hidden => Decoded::Hidden(rec),
+                Verdict::Keep | Verdict::Drop => Decoded::Nothing,
+            },
+            Err(err) => match opts.malformed {
+                Malformed::Skip => Decoded::Nothing,
+                Malformed::Pass if self.window.as_ref().is_some_and(|w| !w.inside) => {
+                    Decoded::Nothing
+                }
+                Malformed::Pass => Decoded::Raw(trimmed.to_string()),
+                Malformed::Report => {
+                    eprintln!("{}: {err}: {trimmed}", self.location());
+                    Decoded::Nothing
+                }
+                Malformed::Strict => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: {err}", self.location()),
+                    ));
+                }
+            },
+        };
+        Ok(decoded)
+    }
+
+    /// `line 12`, or `a.log: line 12`.
+    fn location(&self) -> String {
+        match self.name {
+            Some(name) => format!("{name}: line {}", self.line_no),
+            None => format!("line {}", self.line_no),
+        }
+    }
+}
+
+type Batch = io::Result<Vec<Vec<u8>>>;
+
+/// Read `input` on a detached thread; see [`read_batches`].
+fn spawn_reader<R: Read + Send + 'static>(input: R) -> Receiver<Batch> {
+    let (tx, rx) = mpsc::sync_channel::<Batch>(64);
+    thread::spawn(move || read_batches(input, |batch| tx.send(batch).is_ok()));
+    rx
+}
+
+/// Read `input`, handing every complete line that is already buffered to
+/// `send` as one batch. A read error is sent last. Stops early when `send`
+/// returns false.
+fn read_batches<R: Read>(input: R, mut send: impl FnMut(Batch) -> bool) {
+    let mut reader = BufReader::with_capacity(READ_BUF_SIZE, input);
+    loop {
+        let mut batch = Vec::new();
+        loop {
+            let mut buf = Vec::new();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) => break,
+                Ok(_) => batch.push(buf),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    if send(Ok(batch)) {
+                        send(Err(e));
+                    }
+                    return;
+                }
+            }
+            // Send what we have before a read that may block, including
+            // one that waits for the rest of a partial line.
+            if !reader.buffer().contains(&b'\n') {
+                break;
+            }
+        }
+        if batch.is_empty() || !send(Ok(batch)) {
+            return;
+        }
+    }
+}
+
+/// Like [`for_each_entry`], for consumers that only care about records:
+/// raw lines are dropped even under [`Malformed::Pass`].
+pub fn for_each_record<R, W, F>(input: R, output: W, opts: &ReadOptions, mut f: F) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+    F: FnMut(&Record, &mut BufWriter<W>) -> io::Result<()>,
+{
+    for_each_entry(input, output, opts, |entry, out| match entry {
+        Entry::Record(rec) => f(rec, out),
+        Entry::Raw(_) | Entry::Hidden(_) | Entry::Idle => Ok(()),
+    })
+}
+
+/// Pretty-print `input` to `output` one record at a time.
+pub fn stream_pretty<R, W>(
+    input: R,
+    mut output: W,
+    opts: &ReadOptions,
+    renderer: &mut Renderer,
+) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+{
+    for_each_entry(input, &mut output, opts, |entry, out| match entry {
+        Entry::Record(rec) => renderer.write_record(out, rec),
+        Entry::Hidden(rec) => renderer.write_hidden(out, rec),
+        Entry::Raw(text) => renderer.write_raw(out, text),
+        Entry::Idle => renderer.flush_pending(out),
+    })?;
+    renderer.finish(&mut output)?;
+    output.flush()
+}
+
+/// Pretty-print several inputs merged by time (see
+/// [`for_each_merged_entry`]), input `i` in lane `i`.
+pub fn stream_lanes<R, W>(
+    inputs: Vec<MergeInput<R>>,
+    mut output: W,
+    opts: &ReadOptions,
+    lanes: &mut Lanes,
+) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+{
+    for_each_merged_entry(inputs, &mut output, opts, |i, entry, out| match entry {
+        Entry::Record(rec) => lanes.write_record(out, i, rec),
+        Entry::Hidden(rec) => lanes.write_hidden(out, i, rec),
+        Entry::Raw(text) => lanes.write_raw(out, i, text),
+        Entry::Idle => lanes.flush_pending(out),
+    })?;
+    lanes.finish(&mut output)?;
+    output.flush()
+}
+
+/// Pretty-print `input` thread by thread; see [`Threads`].
+pub fn stream_threads<R, W>(
+    input: R,
+    mut output: W,
+    opts: &ReadOptions,
+    threads: &mut Threads,
+) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+{
+    for_each_entry(input, &mut output, opts, |entry, out| match entry {
+        Entry::Record(rec) => threads.write_record(out, rec),
+        Entry::Hidden(rec) => threads.write_hidden(out, rec),
+        Entry::Raw(text) => threads.write_raw(out, text),
+        Entry::Idle => threads.flush_pending(out),
+    })?;
+    threads.finish(&mut output)?;
+    output.flush()
+}
+
+/// True for the error a write gets once the reading end of a pipe is gone,
+/// e.g. `depthlog_pretty < big.log | head`.
+pub fn is_broken_pipe(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::BrokenPipe
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{Sender, SyncSender};
+
+    type Input = Box<dyn Read + Send>;
+
+    /// An input that is open but only has what is sent to it; it ends when
+    /// the sender is dropped.
+    struct Quiet(Receiver<Vec<u8>>);
+
+    impl Read for Quiet {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Ok(data) = self.0.recv() else {
+                return Ok(0);
+            };
+            buf[..data.len()].copy_from_slice(&data);
+            Ok(data.len())
+        }
+    }
+
+    fn input(name: &str, reader: Input) -> MergeInput<Input> {
+        MergeInput {
+            name: name.to_string(),
+            input: reader,
+            timestamps: TimestampParser::default(),
+        }
+    }
+
+    fn text(lines: &str) -> Input {
+        Box::new(Cursor::new(lines.as_bytes().to_vec()))
+    }
+
+    fn opts() -> ReadOptions {
+        ReadOptions {
+            malformed: Malformed::Pass,
+            ..ReadOptions::default()
+        }
+    }
+
+    /// One "input:text" string per entry: the message of records, the
+    /// line of raw ones.
+    fn describe(i: usize, entry: Entry<'_>) -> Option<String> {
+        match entry {
+            Entry::Record(rec) => Some(format!("{i}:{}", rec.msg.as_deref().unwrap_or(""))),
+            Entry::Raw(text) => Some(format!("{i}:{text}")),
+            Entry::Hidden(_) | Entry::Idle => None,
+        }
+    }
+
+    fn merge(inputs: Vec<MergeInput<Input>>) -> Vec<String> {
+        let mut seen = Vec::new();
+        for_each_merged_entry(inputs, io::sink(), &opts(), |i, entry, _| {
+            seen.extend(describe(i, entry));
+            Ok(())
+        })
+        .unwrap();
+        seen
+    }
+
+    /// Merges on another thread, handing each entry over as it comes.
+    fn merge_in_background(inputs: Vec<MergeInput<Input>>) -> Receiver<String> {
+        let (tx, rx): (Sender<String>, _) = mpsc::channel();
+        thread::spawn(move || {
+            for_each_merged_entry(inputs, io::sink(), &opts(), |i, entry, _| {
+                if let Some(seen) = describe(i, entry) {
+                    let _ = tx.send(seen);
+                }
+                Ok(())
+            })
+        });
+        rx
+    }
+
+    fn quiet() -> (SyncSender<Vec<u8>>, Input) {
+        let (tx, rx) = mpsc::sync_channel(1);
+        (tx, Box::new(Quiet(rx)))
+    }
+
+    #[test]
+    fn records_interleave_by_timestamp() {
+        let a = "ts=2025-01-01T00:00:01Z msg=a1\n\
+                 ts=2025-01-01T00:00:03Z msg=a3\n\
+                 ts=2025-01-01T00:00:05Z msg=a5\n";
+        let b = "ts=2025-01-01T00:00:02Z msg=b2\n\
+                 ts=2025-01-01T00:00:03Z msg=b3\n\
+                 ts=2025-01-01T00:00:06Z msg=b6\n";
+        let seen = merge(vec![input("a", text(a)), input("b", text(b))]);
+        assert_eq!(
+            seen,
+            ["0:a1", "1:b2", "0:a3", "1:b3", "0:a5", "1:b6"].map(String::from)
+        );
+    }
+
+    #[test]
+    fn undated_lines_stay_in_their_lane() {
+        let a = "msg=a-before\n\
+                 ts=2025-01-01T00:00:02Z msg=a2\n\
+                 not logfmt at all\n\
+                 msg=a-after\n\
+                 ts=2025-01-01T00:00:04Z msg=a4\n";
+        let b = "ts=2025-01-01T00:00:01Z msg=b1\n\
+                 ts=2025-01-01T00:00:03Z msg=b3\n\
+                 msg=b-after\n";
+        let seen = merge(vec![input("a", text(a)), input("b", text(b))]);
+        assert_eq!(
+            seen,
+            [
+                "1:b1",
+                "0:a-before",
+                "0:a2",
+                "0:not logfmt at all",
+                "0:a-after",
+                "1:b3",
+                "1:b-after",
+                "0:a4",
+            ]
+            .map(String::from)
+        );
+    }
+
+    #[test]
+    fn a_quiet_input_does_not_hold_back_the_others() {
+        let (quiet_tx, quiet_input) = quiet();
+        let busy = "ts=2025-01-01T00:00:01Z msg=one\n\
+                    ts=2025-01-01T00:00:02Z msg=two\n";
+        let rx = merge_in_background(vec![input("quiet", quiet_input), input("busy", text(busy))]);
+        let wait = Duration::from_secs(5);
+        assert_eq!(rx.recv_timeout(wait).unwrap(), "1:one");
+        assert_eq!(rx.recv_timeout(wait).unwrap(), "1:two");
+
+        // Once it speaks up again, its lines still come through.
+        quiet_tx
+            .send(b"ts=2025-01-01T00:00:03Z msg=late\n".to_vec())
+            .unwrap();
+        assert_eq!(rx.recv_timeout(wait).unwrap(), "0:late");
+        drop(quiet_tx);
+        assert!(rx.recv_timeout(wait).is_err());
+    }
+
+    #[test]
+    fn a_busy_input_goes_on_while_another_is_quiet() {
+        // Without a pause in all input, the merge still stops waiting for
+        // an input that has said nothing for `IDLE_AFTER`, so the lines of
+        // the busy one do not pile up.
+        let (quiet_tx, quiet_input) = quiet();
+        let (busy_tx, busy_input) = quiet();
+        let rx = merge_in_background(vec![input("quiet", quiet_input), input("busy", busy_input)]);
+        quiet_tx
+            .send(b"ts=2025-01-01T00:00:00Z msg=q\n".to_vec())
+            .unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let feeder = thread::spawn({
+            let stop = Arc::clone(&stop);
+            move || {
+                for n in 0..1000 {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let line = format!("ts=2025-01-01T00:01:00Z msg=b{n}\n");
+                    busy_tx.send(line.into_bytes()).unwrap();
+                    thread::sleep(IDLE_AFTER / 5);
+                }
+            }
+        });
+        let wait = Duration::from_secs(5);
+        assert_eq!(rx.recv_timeout(wait).unwrap(), "0:q");
+        assert_eq!(rx.recv_timeout(wait).unwrap(), "1:b0");
+        stop.store(true, Ordering::Relaxed);
+        feeder.join().unwrap();
+        drop(quiet_tx);
+    }
+}
@@ -0,0 +1,213 @@
+// ---------- subtree selection ----------
+//
+// `SubtreeFilter` keeps only the invocations of matching functions, with
+// everything they call, as if each were a root: depths are shifted so the
+// matched call is at depth 0. `Pruner` does the opposite and collapses
+// matching invocations to their entry line. Invocations are delimited by the
+// same rules as `CallTree`, on the fly, so both work on streams.
+
+use crate::calltree::closes;
+use crate::glob::Glob;
+use crate::record::Record;
+use crate::time::{Timestamp, TimestampParser};
+
+/// What [`SubtreeFilter::select`] decided for a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// Outside every matching invocation.
+    Skip,
+    /// Inside one; the record with its depth rebased.
+    Keep(Record),
+    /// The first record of a matching invocation, rebased to depth 0, and
+    /// the functions of its callers, outermost first.
+    Enter(Record, Vec<String>),
+}
+
+/// Selects the subtrees of the invocations whose `func` matches a glob.
+#[derive(Debug, Clone)]
+pub struct SubtreeFilter {
+    pattern: Glob,
+    /// Open invocations as `(depth, func)`, shallowest first.
+    open: Vec<(usize, Option<String>)>,
+    /// Index in `open` of the matched invocation being passed through.
+    selected: Option<usize>,
+}
+
+impl SubtreeFilter {
+    pub fn new(pattern: Glob) -> Self {
+        SubtreeFilter {
+            pattern,
+            open: Vec::new(),
+            selected: None,
+        }
+    }
+
+    /// Whether the last record was inside a selected subtree; lines that
+    /// are not records are kept or dropped along with it.
+    pub fn inside(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    pub fn select(&mut self, rec: &Record) -> Selection {
+        while let Some((depth, func)) = self.open.last() {
+            if !closes(*depth, func.as_deref(), rec) {
+                break;
+            }
+            self.open.pop();
+        }
+        if self.selected.is_some_and(|i| i >= self.open.len()) {
+            self.selected = None;
+        }
+        let entered = match self.open.last() {
+            Some((depth, _)) if *depth == rec.depth => false,
+            _ => {
+                self.open.push((rec.depth, rec.func.clone()));
+                true
+            }
+        };
+
+        if let Some(i) = self.selected {
+            return Selection::Keep(rebase(rec, self.open[i].0));
+        }
+        let matched = rec.func.as_deref().is_some_and(|f| self.pattern.matches(f));
+        if !entered || !matched {
+            return Selection::Skip;
+        }
+        let current = self.open.len() - 1;
+        self.selected = Some(current);
+        let callers = self.open[..current]
+            .iter()
+            .map(|(_, func)| func.as_deref().unwrap_or("?").to_string())
+            .collect();
+        Selection::Enter(rebase(rec, rec.depth), callers)
+    }
+}
+
+fn rebase(rec: &Record, base: usize) -> Record {
+    let mut rec = rec.clone();
+    rec.depth -= base;
+    rec
+}
+
+// ---------- pruning ----------
+
+/// A pruned invocation, reported when it returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pruned {
+    pub func: Option<String>,
+    pub depth: usize,
+    /// Records hidden after the entry line.
+    pub hidden: usize,
+    /// Entry to the last hidden record, in nanoseconds.
+    pub elapsed: Option<i128>,
+}
+
+/// What [`Pruner::push`] decided for a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pruning {
+    pub show: bool,
+    /// The pruned invocation this record ended, if any.
+    pub ended: Option<Pruned>,
+}
+
+#[derive(Debug, Clone)]
+struct Hiding {
+    /// Index in `open` of the pruned invocation.
+    index: usize,
+    func: Option<String>,
+    depth: usize,
+    hidden: usize,
+    first: Option<Timestamp>,
+    last: Option<Timestamp>,
+}
+
+impl Hiding {
+    fn into_pruned(self) -> Pruned {
+        let elapsed = match (self.first, self.last) {
+            (Some(first), Some(last)) => Some(last.since(&first)),
+            _ => None,
+        };
+        Pruned {
+            func: self.func,
+            depth: self.depth,
+            hidden: self.hidden,
+            elapsed,
+        }
+    }
+}
+
+/// Shows only the entry line of the invocations of matching functions,
+/// hiding everything else they log or call.
+#[derive(Debug, Clone)]
+pub struct Pruner {
+    patterns: Vec<Glob>,
+    timestamps: TimestampParser,
+    /// Open invocations as `(depth, func)`, shallowest first.
+    open: Vec<(usize, Option<String>)>,
+    hiding: Option<Hiding>,
+}
+
+impl Pruner {
+    pub fn new(patterns: Vec<Glob>, timestamps: TimestampParser) -> Self {
+        Pruner {
+            patterns,
+            timestamps,
+            open: Vec::new(),
+            hiding: None,
+        }
+    }
+
+    /// Whether a pruned invocation is open; lines that are not records are
+    /// hidden along with it.
+    pub fn inside(&self) -> bool {
+        self.hiding.is_some()
+    }
+
+    pub fn push(&mut self, rec: &Record) -> Pruning {
+        while let Some((depth, func)) = self.open.last() {
+            if !closes(*depth, func.as_deref(), rec) {
+                break;
+            }
+            self.open.pop();
+        }
+        let ended = match &self.hiding {
+            Some(h) if h.index >= self.open.len() => self.finish(),
+            _ => None,
+        };
+        let entered = match self.open.last() {
+            Some((depth, _)) if *depth == rec.depth => false,
+            _ => {
+                self.open.push((rec.depth, rec.func.clone()));
+                true
+            }
+        };
+
+        let ts = rec.ts.as_deref().and_then(|t| self.timestamps.parse(t));
+        if let Some(h) = &mut self.hiding {
+            h.hidden += 1;
+            h.last = ts.or(h.last);
+            return Pruning { show: false, ended };
+        }
+        let matched = rec
+            .func
+            .as_deref()
+            .is_some_and(|f| self.patterns.iter().any(|p| p.matches(f)));
+        if entered && matched {
+            self.hiding = Some(Hiding {
+                index: self.open.len() - 1,
+                func: rec.func.clone(),
+                depth: rec.depth,
+                hidden: 0,
+                first: ts,
+                last: ts,
+            });
+        }
+        Pruning { show: true, ended }
+    }
+
+    /// End of input (or of a stretch of it): the open pruned invocation, if
+    /// any, returns.
+    pub fn finish(&mut self) -> Option<Pruned> {
+        self.hiding.take().map(Hiding::into_pruned)
+    }
+}
@@ -0,0 +1,184 @@
+// ---------- interactive terminal ----------
+//
+// Just enough terminal handling for `depthlog view`, without dependencies:
+// the controlling terminal is opened as `/dev/tty` (stdin may be the log
+// itself), switched to non-canonical mode through `stty`, and drawn on the
+// alternate screen with plain ANSI escapes. Keys are decoded from the raw
+// bytes read off the terminal.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+/// A decoded key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    /// `Ctrl` plus a letter, lowercase.
+    Ctrl(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Tab,
+    BackTab,
+    Backspace,
+    Esc,
+    /// An escape sequence this module does not know.
+    Unknown,
+}
+
+/// Decode the bytes of one read from the terminal. A read returns whole
+/// escape sequences in practice, so a lone `ESC` at the end is the Esc key.
+pub fn decode_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        i += 1;
+        let key = match b {
+            0x1b => match bytes.get(i) {
+                Some(b'[' | b'O') => {
+                    let start = i + 1;
+                    let end = bytes[start..]
+                        .iter()
+                        .position(|c| (0x40..=0x7e).contains(c))
+                        .map_or(bytes.len(), |p| start + p + 1);
+                    i = end;
+                    csi_key(&bytes[start..end])
+                }
+                _ => Key::Esc,
+            },
+            b'\r' | b'\n' => Key::Enter,
+            b'\t' => Key::Tab,
+            0x7f | 0x08 => Key::Backspace,
+            0x01..=0x1a => Key::Ctrl(char::from(b'a' + b - 1)),
+            0x00..=0x7f => Key::Char(char::from(b)),
+            _ => {
+                // UTF-8: the leading byte tells the length.
+                let len = match b {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    _ => 4,
+                };
+                let end = (i - 1 + len).min(bytes.len());
+                let key = std::str::from_utf8(&bytes[i - 1..end])
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                    .map_or(Key::Unknown, Key::Char);
+                i = end;
+                key
+            }
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// The key for the parameters and final byte of `ESC [` / `ESC O`.
+fn csi_key(seq: &[u8]) -> Key {
+    match seq {
+        b"A" => Key::Up,
+        b"B" => Key::Down,
+        b"C" => Key::Right,
+        b"D" => Key::Left,
+        b"H" | b"1~" | b"7~" => Key::Home,
+        b"F" | b"4~" | b"8~" => Key::End,
+        b"5~" => Key::PageUp,
+        b"6~" => Key::PageDown,
+        b"Z" => Key::BackTab,
+        _ => Key::Unknown,
+    }
+}
+
+/// The controlling terminal in non-canonical mode on the alternate screen;
+/// dropping it restores the previous state.
+pub struct Terminal {
+    tty: File,
+    /// `stty -g` output from before, to restore.
+    saved: String,
+}
+
+impl Terminal {
+    pub fn open() -> io::Result<Self> {
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let saved = stty(&tty, &["-g"])?;
+        // `time 2`: a read returns after 0.2s without input, so the caller
+        // can notice a resize.
+        stty(
+            &tty,
+            &[
+                "-icanon", "-echo", "-isig", "-ixon", "min", "0", "time", "2",
+            ],
+        )?;
+        let mut term = Terminal {
+            tty,
+            saved: saved.trim().to_string(),
+        };
+        term.write_frame("\x1b[?1049h\x1b[?25l")?;
+        Ok(term)
+    }
+
+    /// The terminal handle, e.g. to decide whether to use colors.
+    pub fn tty(&self) -> &File {
+        &self.tty
+    }
+
+    /// `(rows, columns)`, or 24x80 when the size is unknown.
+    pub fn size(&self) -> (usize, usize) {
+        let size = stty(&self.tty, &["size"]).ok().and_then(|out| {
+            let (rows, cols) = out.trim().split_once(' ')?;
+            Some((rows.parse().ok()?, cols.parse().ok()?))
+        });
+        match size {
+            Some((rows, cols)) if rows > 0 && cols > 0 => (rows, cols),
+            _ => (24, 80),
+        }
+    }
+
+    /// Wait up to 0.2s for input; empty when none arrived.
+    pub fn read_keys(&mut self) -> io::Result<Vec<Key>> {
+        let mut buf = [0u8; 64];
+        let n = self.tty.read(&mut buf)?;
+        Ok(decode_keys(&buf[..n]))
+    }
+
+    pub fn write_frame(&mut self, frame: &str) -> io::Result<()> {
+        self.tty.write_all(frame.as_bytes())?;
+        self.tty.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.write_frame("\x1b[?25h\x1b[?1049l");
+        let _ = stty(&self.tty, &[self.saved.as_str()]);
+    }
+}
+
+/// Columns of the controlling terminal, when there is one; for output that
+/// is not drawn through [`Terminal`].
+pub fn terminal_width() -> Option<usize> {
+    let tty = File::open("/dev/tty").ok()?;
+    let out = stty(&tty, &["size"]).ok()?;
+    let (_, cols) = out.trim().split_once(' ')?;
+    cols.parse().ok().filter(|&cols| cols > 0)
+}
+
+/// Run `stty` on `tty`; returns its standard output.
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+    let out = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone()?)
+        .stderr(Stdio::null())
+        .output()?;
+    if !out.status.success() {
+        return Err(io::Error::other(format!("stty {} failed", args.join(" "))));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
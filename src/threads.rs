@@ -0,0 +1,620 @@
+// ---------- per-thread demultiplexing ----------
+//
+// Records of different threads interleave in one log, each thread with its
+// own call stack. `Threads` sorts them out by a thread-id field before
+// rendering: every thread gets its own `Renderer` (through `Lanes`), so
+// depths, call timing and pruning are tracked per thread. The side-by-side
+// layout puts the lines of those renderers in rows, one column per thread.
+
+use std::io::{self, Write};
+
+use crate::color::{truncate_visible, visible_width};
+use crate::lanes::Lanes;
+use crate::record::Record;
+use crate::render::{RenderConfig, Renderer};
+use crate::time::{TimeFormat, TimeMode, Timestamp};
+use crate::timing::CallTimer;
+
+/// The field or fields naming the thread of a record: `tid`, or `pid:tid`
+/// for several joined by `:`. Alternatives are separated by commas and the
+/// first one present wins; the default is `tid,thread`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadKey {
+    alternatives: Vec<Vec<String>>,
+}
+
+impl Default for ThreadKey {
+    fn default() -> Self {
+        ThreadKey {
+            alternatives: vec![vec!["tid".to_string()], vec!["thread".to_string()]],
+        }
+    }
+}
+
+impl std::str::FromStr for ThreadKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let alternatives: Vec<Vec<String>> = s
+            .split(',')
+            .map(|alt| alt.split(':').map(|k| k.trim().to_string()).collect())
+            .collect();
+        if alternatives.iter().flatten().any(String::is_empty) {
+            return Err(format!(
+                "expected field names like tid, thread or pid:tid, got `{s}`"
+            ));
+        }
+        Ok(ThreadKey { alternatives })
+    }
+}
+
+impl ThreadKey {
+    /// The thread id of `rec` and the key it was found by: the values of
+    /// the first alternative whose fields are all present, joined by `:`,
+    /// and the names of those fields, likewise.
+    pub fn id(&self, rec: &Record) -> Option<(String, String)> {
+        self.alternatives.iter().find_map(|fields| {
+            let values = fields
+                .iter()
+                .map(|k| rec.extra(k))
+                .collect::<Option<Vec<&str>>>()?;
+            Some((fields.join(":"), values.join(":")))
+        })
+    }
+
+    /// Every field any alternative reads.
+    fn fields(&self) -> impl Iterator<Item = &String> {
+        self.alternatives.iter().flatten()
+    }
+
+    /// How headings name records without a thread id: by the first
+    /// alternative.
+    fn fallback_name(&self) -> String {
+        self.alternatives
+            .first()
+            .map_or_else(|| "thread".to_string(), |fields| fields.join(":"))
+    }
+}
+
+/// How the threads of a log are laid out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThreadMode {
+    /// In input order, each line starting with a colored thread label.
+    Interleave,
+    /// Each thread's records together, threads in order of appearance,
+    /// under a heading. Waits for the end of input.
+    Group,
+    /// One column per thread, side by side, records in timestamp order
+    /// in their thread's column. Waits for the end of input.
+    Columns,
+    /// Only the records of this thread.
+    Only(String),
+}
+
+impl std::str::FromStr for ThreadMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "interleave" => Ok(ThreadMode::Interleave),
+            "group" => Ok(ThreadMode::Group),
+            "columns" => Ok(ThreadMode::Columns),
+            other => Err(format!(
+                "expected interleave, group or columns, got `{other}`"
+            )),
+        }
+    }
+}
+
+/// What [`ThreadMode::Group`] and [`ThreadMode::Columns`] hold until the
+/// end.
+enum Buffered {
+    Record(Record),
+    Hidden(Record),
+    Raw(String),
+}
+
+/// Label of records without a thread id.
+const NO_THREAD: &str = "?";
+
+/// Routes records to per-thread renderers. Lines that are not records go
+/// with the thread of the record before them.
+pub struct Threads {
+    key: ThreadKey,
+    mode: ThreadMode,
+    config: RenderConfig,
+    /// Thread ids with the key that named them first, in order of
+    /// appearance; the index is the lane.
+    ids: Vec<(String, String)>,
+    /// Lane of the last record.
+    current: Option<usize>,
+    lanes: Lanes,
+    /// For [`ThreadMode::Group`], the entries of each lane.
+    groups: Vec<Vec<Buffered>>,
+    /// For [`ThreadMode::Columns`], every entry with its lane and the time
+    /// it sorts by.
+    rows: Vec<(Option<Timestamp>, usize, Buffered)>,
+    last_ts: Option<Timestamp>,
+    /// Total width for [`ThreadMode::Columns`].
+    width: usize,
+    /// For [`ThreadMode::Only`].
+    single: Renderer,
+}
+
+impl Threads {
+    pub fn new(mut config: RenderConfig, key: ThreadKey, mode: ThreadMode) -> Self {
+        // Labels, headings and column headers already name the thread.
+        if !matches!(mode, ThreadMode::Only(_)) {
+            config.extra_exclude.extend(key.fields().cloned());
+        }
+        Threads {
+            key,
+            mode,
+            lanes: Lanes::new(&config),
+            single: Renderer::new(config.clone()),
+            config,
+            ids: Vec::new(),
+            current: None,
+            groups: Vec::new(),
+            rows: Vec::new(),
+            last_ts: None,
+            width: 80,
+        }
+    }
+
+    /// The width [`ThreadMode::Columns`] fills, normally the terminal's.
+    pub fn set_width(&mut self, width: usize) {
+        self.width = width;
+    }
+
+    pub fn write_record<W: Write>(&mut self, w: &mut W, rec: &Record) -> io::Result<()> {
+        let (key, id) = match self.key.id(rec) {
+            Some((key, id)) => (Some(key), id),
+            None => (None, NO_THREAD.to_string()),
+        };
+        if let ThreadMode::Only(only) = &self.mode {
+            self.current = (id == *only).then_some(0);
+            return match self.current {
+                Some(_) => self.single.write_record(w, rec),
+                None => Ok(()),
+            };
+        }
+        let lane = self.lane(key, &id);
+        self.current = Some(lane);
+        match self.mode {
+            ThreadMode::Group => {
+                self.groups[lane].push(Buffered::Record(rec.clone()));
+                Ok(())
+            }
+            ThreadMode::Columns => {
+                if let Some(ts) = rec
+                    .ts
+                    .as_deref()
+                    .and_then(|t| self.config.timestamps.parse(t))
+                {
+                    self.last_ts = Some(ts);
+                }
+                self.rows
+                    .push((self.last_ts, lane, Buffered::Record(rec.clone())));
+                Ok(())
+            }
+            _ => self.lanes.write_record(w, lane, rec),
+        }
+    }
+
+    /// [`Renderer::write_hidden`] for the thread of `rec`, once it has
+    /// records to show.
+    pub fn write_hidden<W: Write>(&mut self, w: &mut W, rec: &Record) -> io::Result<()> {
+        let id = self.key.id(rec).map(|(_, id)| id);
+        let id = id.as_deref().unwrap_or(NO_THREAD);
+        if let ThreadMode::Only(only) = &self.mode {
+            if id != only {
+                return Ok(());
+            }
+            return self.single.write_hidden(w, rec);
+        }
+        let Some(lane) = self.ids.iter().position(|(_, t)| t == id) else {
+            return Ok(());
+        };
+        match self.mode {
+            ThreadMode::Group => {
+                self.groups[lane].push(Buffered::Hidden(rec.clone()));
+                Ok(())
+            }
+            ThreadMode::Columns => {
+                self.rows
+                    .push((self.last_ts, lane, Buffered::Hidden(rec.clone())));
+                Ok(())
+            }
+            _ => self.lanes.write_hidden(w, lane, rec),
+        }
+    }
+
+    pub fn write_raw<W: Write>(&mut self, w: &mut W, text: &str) -> io::Result<()> {
+        let lane = match (&self.mode, self.current) {
+            (ThreadMode::Only(_), Some(_)) => return self.single.write_raw(w, text),
+            (ThreadMode::Only(_), None) => return Ok(()),
+            (_, Some(lane)) => lane,
+            (_, None) => self.lane(None, NO_THREAD),
+        };
+        match self.mode {
+            ThreadMode::Group => {
+                self.groups[lane].push(Buffered::Raw(text.to_string()));
+                Ok(())
+            }
+            ThreadMode::Columns => {
+                self.rows
+                    .push((self.last_ts, lane, Buffered::Raw(text.to_string())));
+                Ok(())
+            }
+            _ => self.lanes.write_raw(w, lane, text),
+        }
+    }
+
+    pub fn flush_pending<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        match self.mode {
+            ThreadMode::Interleave => self.lanes.flush_pending(w),
+            ThreadMode::Group | ThreadMode::Columns => Ok(()),
+            ThreadMode::Only(_) => self.single.flush_pending(w),
+        }
+    }
+
+    /// End of input; in [`ThreadMode::Group`] and [`ThreadMode::Columns`]
+    /// this writes everything.
+    pub fn finish<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        match self.mode {
+            ThreadMode::Interleave => self.lanes.finish(w),
+            ThreadMode::Only(_) => self.single.finish(w),
+            ThreadMode::Columns => self.write_columns(w),
+            ThreadMode::Group => {
+                for ((key, id), entries) in self.ids.iter().zip(std::mem::take(&mut self.groups)) {
+                    let mut renderer = Renderer::new(self.config.clone());
+                    let shown = entries
+                        .iter()
+                        .filter(|e| !matches!(e, Buffered::Hidden(_)))
+                        .count();
+                    let plural = if shown == 1 { "" } else { "s" };
+                    let heading = format!("{key}={id} ({shown} line{plural})");
+                    renderer.write_separator(w, &heading)?;
+                    for entry in entries {
+                        match entry {
+                            Buffered::Record(rec) => renderer.write_record(w, &rec)?,
+                            Buffered::Hidden(rec) => renderer.write_hidden(w, &rec)?,
+                            Buffered::Raw(text) => renderer.write_raw(w, &text)?,
+                        }
+                    }
+                    renderer.finish(w)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// The side-by-side layout, a header naming the threads and then one
+    /// row per line of their renderers:
+    ///
+    /// ```text
+    ///              │ tid=1               │ tid=2
+    /// 09:00:00.002 │                     │ [D] | worker: up
+    /// 09:00:00.003 │                     │ [D] |     lock: acquire
+    /// ```
+    ///
+    /// Cells are what the thread's [`Renderer`] writes without the time and
+    /// location, cut to the column width; the time column is shared.
+    fn write_columns<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        let mut rows = std::mem::take(&mut self.rows);
+        rows.sort_by_key(|(ts, _, _)| *ts);
+        let format = self.config.time_format;
+        let time_width = format.placeholder().chars().count();
+        let lanes = self.ids.len().max(1);
+        // Each lane is preceded by ` │ `.
+        let cell_width = (self.width.saturating_sub(time_width) / lanes)
+            .saturating_sub(3)
+            .max(1);
+
+        let blank = " ".repeat(time_width);
+        let header: Vec<String> = self
+            .ids
+            .iter()
+            .map(|(key, id)| format!("{key}={id}"))
+            .collect();
+        let header: Vec<&str> = header.iter().map(String::as_str).collect();
+        write_row(w, &blank, &header, cell_width)?;
+
+        // The renderers write an absolute time column only to tell record
+        // lines from the lines they bring along, whose time column is
+        // blank; the shared one replaces it.
+        let config = RenderConfig {
+            show_time: true,
+            show_location: false,
+            time_format: TimeFormat {
+                mode: TimeMode::Absolute,
+                ..format
+            },
+            ..self.config.clone()
+        };
+        let lead = config.time_format.placeholder().chars().count() + 1;
+        let mut renderers: Vec<Renderer> = self
+            .ids
+            .iter()
+            .map(|_| Renderer::new(config.clone()))
+            .collect();
+        let mut timers: Vec<CallTimer> = self
+            .ids
+            .iter()
+            .map(|_| CallTimer::new(self.config.timestamps.clone()))
+            .collect();
+        let (mut first, mut prev) = (None, None);
+        let mut buf = Vec::new();
+        for (_, lane, entry) in rows {
+            let renderer = &mut renderers[lane];
+            let rec = match entry {
+                Buffered::Record(rec) => {
+                    timers[lane].push(&rec);
+                    renderer.write_record(&mut buf, &rec)?;
+                    Some(rec)
+                }
+                Buffered::Hidden(rec) => {
+                    timers[lane].push_hidden(&rec);
+                    renderer.write_hidden(&mut buf, &rec)?;
+                    None
+                }
+                Buffered::Raw(text) => {
+                    // Not held for the next record of the thread, which
+                    // may be many rows later.
+                    renderer.write_raw(&mut buf, &text)?;
+                    renderer.flush_pending(&mut buf)?;
+                    None
+                }
+            };
+            let out = String::from_utf8_lossy(&buf);
+            for line in out.lines() {
+                let (head, cell) = split_chars(line, lead);
+                let time = match &rec {
+                    Some(rec) if !head.starts_with(' ') => {
+                        let ts = rec
+                            .ts
+                            .as_deref()
+                            .and_then(|t| self.config.timestamps.parse(t));
+                        let time = match ts {
+                            None => format.placeholder(),
+                            Some(t) => match format.mode {
+                                TimeMode::Absolute => format.format(&t),
+                                TimeMode::Relative => {
+                                    format.format_elapsed(t.since(first.get_or_insert(t)))
+                                }
+                                TimeMode::Delta => format
+                                    .format_elapsed(prev.map_or(0, |p: Timestamp| t.since(&p))),
+                                TimeMode::Call => format.format_elapsed(
+                                    timers[lane].entry().map_or(0, |e| t.since(&e)),
+                                ),
+                            },
+                        };
+                        if ts.is_some() {
+                            first = first.or(ts);
+                            prev = ts;
+                        }
+                        time
+                    }
+                    _ => blank.clone(),
+                };
+                let mut cells = vec![""; lanes];
+                cells[lane] = cell;
+                write_row(w, &time, &cells, cell_width)?;
+            }
+            buf.clear();
+        }
+        for (lane, renderer) in renderers.iter_mut().enumerate() {
+            renderer.finish(&mut buf)?;
+            for line in String::from_utf8_lossy(&buf).lines() {
+                let mut cells = vec![""; lanes];
+                cells[lane] = split_chars(line, lead).1;
+                write_row(w, &blank, &cells, cell_width)?;
+            }
+            buf.clear();
+        }
+        Ok(())
+    }
+
+    /// The lane of thread `id`, found by `key`, opened on first sight.
+    fn lane(&mut self, key: Option<String>, id: &str) -> usize {
+        if let Some(lane) = self.ids.iter().position(|(_, t)| t == id) {
+            return lane;
+        }
+        let key = key.unwrap_or_else(|| self.key.fallback_name());
+        self.ids.push((key, id.to_string()));
+        self.groups.push(Vec::new());
+        self.lanes.add(id)
+    }
+}
+
+/// One row of the side-by-side layout, a cell per lane.
+fn write_row<W: Write>(w: &mut W, time: &str, cells: &[&str], width: usize) -> io::Result<()> {
+    let mut line = time.to_string();
+    for (lane, cell) in cells.iter().enumerate() {
+        line.push_str(" │ ");
+        let fitted = fit(cell, width);
+        line.push_str(&fitted);
+        if lane + 1 < cells.len() {
+            line.extend(std::iter::repeat_n(' ', width - visible_width(&fitted)));
+        }
+    }
+    writeln!(w, "{}", line.trim_end())
+}
+
+/// `line` split after its first `n` characters.
+fn split_chars(line: &str, n: usize) -> (&str, &str) {
+    let at = line.char_indices().nth(n).map_or(line.len(), |(i, _)| i);
+    line.split_at(at)
+}
+
+/// `cell` cut to `width` columns, with `…` when something was cut.
+fn fit(cell: &str, width: usize) -> String {
+    if visible_width(cell) <= width {
+        return cell.to_string();
+    }
+    let mut cut = truncate_visible(cell, width.saturating_sub(1));
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(key: &str, line: &str) -> Option<(String, String)> {
+        let key: ThreadKey = key.parse().unwrap();
+        key.id(&Record::parse(line).unwrap())
+    }
+
+    fn pair(key: &str, id: &str) -> Option<(String, String)> {
+        Some((key.to_string(), id.to_string()))
+    }
+
+    #[test]
+    fn first_present_alternative_names_the_thread() {
+        assert_eq!(id("tid,thread", "func=f tid=7"), pair("tid", "7"));
+        assert_eq!(
+            id("tid,thread", "func=f thread=main"),
+            pair("thread", "main")
+        );
+        assert_eq!(
+            id("tid,thread", "func=f tid=7 thread=main"),
+            pair("tid", "7")
+        );
+        assert_eq!(id("tid,thread", "func=f"), None);
+    }
+
+    #[test]
+    fn joined_fields_need_all_present() {
+        assert_eq!(id("pid:tid,tid", "pid=3 tid=7"), pair("pid:tid", "3:7"));
+        assert_eq!(id("pid:tid,tid", "tid=7"), pair("tid", "7"));
+        assert_eq!(id("pid:tid", "pid=3"), None);
+    }
+
+    #[test]
+    fn empty_field_names_are_rejected() {
+        assert_eq!(
+            "tid,".parse::<ThreadKey>(),
+            Err("expected field names like tid, thread or pid:tid, got `tid,`".to_string())
+        );
+        assert!("pid:".parse::<ThreadKey>().is_err());
+        assert_eq!(
+            " tid , thread ".parse::<ThreadKey>(),
+            Ok(ThreadKey::default())
+        );
+    }
+
+    #[test]
+    fn columns_show_extra_fields_and_wide_chars() {
+        let mut threads = Threads::new(
+            RenderConfig::default(),
+            ThreadKey::default(),
+            ThreadMode::Columns,
+        );
+        threads.set_width(54);
+        let mut out = Vec::new();
+        for line in [
+            "ts=2025-02-15T09:00:00.000Z level=info func=a msg=\u{65e5}\u{672c}\u{8a9e} tid=1 k=v",
+            "ts=2025-02-15T09:00:00.001Z level=debug depth=1 func=b msg=x thread=w",
+        ] {
+            threads
+                .write_record(&mut out, &Record::parse(line).unwrap())
+                .unwrap();
+        }
+        threads.finish(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "             │ tid=1              │ thread=w",
+                "09:00:00.000 │ [I] | a: \u{65e5}\u{672c}\u{8a9e} k… │",
+                "09:00:00.001 │                    │ [D] |     b: x",
+            ]
+        );
+    }
+
+    fn columns(width: usize, config: RenderConfig, lines: &[&str]) -> Vec<String> {
+        let mut threads = Threads::new(config, ThreadKey::default(), ThreadMode::Columns);
+        threads.set_width(width);
+        let mut out = Vec::new();
+        for line in lines {
+            threads
+                .write_record(&mut out, &Record::parse(line).unwrap())
+                .unwrap();
+        }
+        threads.finish(&mut out).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn columns_show_what_the_thread_renderers_write() {
+        let config = RenderConfig {
+            durations: true,
+            gap_threshold: Some(5_000_000),
+            max_depth: Some(1),
+            ..RenderConfig::default()
+        };
+        let lines = columns(
+            90,
+            config,
+            &[
+                "ts=2025-02-15T09:00:00.000Z func=main msg=start tid=1",
+                "ts=2025-02-15T09:00:00.001Z depth=1 func=run msg=go tid=1",
+                "ts=2025-02-15T09:00:00.002Z depth=2 func=deep msg=hidden tid=1",
+                "ts=2025-02-15T09:00:00.003Z func=worker msg=up tid=2",
+                "ts=2025-02-15T09:00:00.010Z func=main msg=end tid=1",
+            ],
+        );
+        assert_eq!(
+            lines,
+            [
+                "             │ tid=1                                │ tid=2",
+                "09:00:00.000 │ [?] | main: start                    │",
+                "09:00:00.001 │ [?] |     run: go                    │",
+                "09:00:00.003 │                                      │ [?] | worker: up",
+                "             │ [<] |     ← run 1.0ms (self 1.0ms)   │",
+                "             │ [:] |     ⋮ 9.0ms gap                │",
+                "09:00:00.010 │ [?] | main: end                      │",
+                "             │ [<] | ← main 10.0ms (self 9.0ms)     │",
+            ]
+        );
+    }
+
+    #[test]
+    fn columns_bound_the_indent_of_huge_depths() {
+        let lines = columns(
+            40,
+            RenderConfig::default(),
+            &["ts=2025-02-15T09:00:00.000Z depth=18446744073709551615 func=f msg=m tid=1"],
+        );
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("09:00:00.000 │ [?] |     "));
+        assert!(visible_width(&lines[1]) <= 40);
+    }
+
+    #[test]
+    fn thread_labels_replace_the_key_fields() {
+        let config = RenderConfig {
+            show_time: false,
+            show_location: false,
+            ..RenderConfig::default()
+        };
+        let key: ThreadKey = "pid:tid".parse().unwrap();
+        let mut out = Vec::new();
+        let mut threads = Threads::new(config.clone(), key.clone(), ThreadMode::Interleave);
+        let rec = Record::parse("func=f msg=m pid=3 tid=7 k=v").unwrap();
+        threads.write_record(&mut out, &rec).unwrap();
+        let mut only = Threads::new(config, key, ThreadMode::Only("3:7".to_string()));
+        only.write_record(&mut out, &rec).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "3:7 [?] | f: m k=v\n[?] | f: m pid=3 tid=7 k=v\n"
+        );
+    }
+}
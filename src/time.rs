@@ -0,0 +1,920 @@
+// ---------- timestamp formatting ----------
+
+use std::sync::{Arc, OnceLock};
+
+use crate::tz::Zone;
+
+/// Reduce an RFC 3339 timestamp to `HH:MM:SS.mmm`, the wall-clock time in
+/// the offset it was written with.
+///
+/// Returns `None` when `ts` does not parse (see [`parse_rfc3339`]).
+pub fn format_time_hms_millis(ts: &str) -> Option<String> {
+    parse_rfc3339(ts).map(|t| TimeFormat::default().format(&t))
+}
+
+/// The UTC offset timestamps are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisplayZone {
+    /// Each timestamp in the offset it was written with.
+    #[default]
+    AsWritten,
+    Utc,
+    /// The system time zone (`TZ`, else /etc/localtime).
+    Local,
+    /// A fixed offset, in seconds east of UTC.
+    Fixed(i32),
+}
+
+impl std::str::FromStr for DisplayZone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "original" | "as-written" => Ok(DisplayZone::AsWritten),
+            "utc" | "UTC" | "Z" => Ok(DisplayZone::Utc),
+            "local" => Ok(DisplayZone::Local),
+            other => parse_offset(other).map(DisplayZone::Fixed).ok_or_else(|| {
+                format!("expected original, utc, local or an offset like +09:00, got `{other}`")
+            }),
+        }
+    }
+}
+
+/// `+09:00`, `-0530` or `+09` as seconds east of UTC.
+fn parse_offset(s: &str) -> Option<i32> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    // Only ASCII from here on, so the slicing below stays on char
+    // boundaries.
+    if !rest.bytes().all(|c| c.is_ascii_digit() || c == b':') {
+        return None;
+    }
+    let (h, m) = match rest.len() {
+        5 if rest.as_bytes()[2] == b':' => (&rest[..2], &rest[3..]),
+        4 => (&rest[..2], &rest[2..]),
+        2 => (rest, "00"),
+        _ => return None,
+    };
+    if !h.bytes().chain(m.bytes()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let h: i32 = h.parse().ok()?;
+    let m: i32 = m.parse().ok()?;
+    (h <= 23 && m <= 59).then_some(sign * (h * 3600 + m * 60))
+}
+
+/// Digits after the seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    Seconds,
+    #[default]
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl Precision {
+    fn digits(self) -> u32 {
+        match self {
+            Precision::Seconds => 0,
+            Precision::Millis => 3,
+            Precision::Micros => 6,
+            Precision::Nanos => 9,
+        }
+    }
+}
+
+impl std::str::FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "s" => Ok(Precision::Seconds),
+            "ms" => Ok(Precision::Millis),
+            "us" | "µs" => Ok(Precision::Micros),
+            "ns" => Ok(Precision::Nanos),
+            other => Err(format!("expected s, ms, us or ns, got `{other}`")),
+        }
+    }
+}
+
+/// What the time column measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeMode {
+    /// The wall-clock time.
+    #[default]
+    Absolute,
+    /// Time since the first record.
+    Relative,
+    /// Time since the previous record.
+    Delta,
+    /// Time since the entry of the call the record belongs to.
+    Call,
+}
+
+impl std::str::FromStr for TimeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absolute" => Ok(TimeMode::Absolute),
+            "relative" => Ok(TimeMode::Relative),
+            "delta" => Ok(TimeMode::Delta),
+            "call" => Ok(TimeMode::Call),
+            other => Err(format!(
+                "expected absolute, relative, delta or call, got `{other}`"
+            )),
+        }
+    }
+}
+
+/// How the time column shows a timestamp: `HH:MM:SS.mmm` by default, with
+/// more or fewer digits, an optional `YYYY-MM-DD ` date, in some zone.
+/// Fractions are truncated, not rounded.
+///
+/// Outside [`TimeMode::Absolute`] the column holds elapsed seconds instead
+/// (`+1.250`, see [`TimeFormat::format_elapsed`]); zone and date do not
+/// apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TimeFormat {
+    pub mode: TimeMode,
+    pub zone: DisplayZone,
+    pub precision: Precision,
+    pub date: bool,
+}
+
+impl TimeFormat {
+    /// Nanoseconds since the epoch of the wall-clock time in the display
+    /// zone.
+    fn wall_nanos(&self, t: &Timestamp) -> i128 {
+        let offset = match self.zone {
+            DisplayZone::AsWritten => return t.local_nanos(),
+            DisplayZone::Utc => 0,
+            DisplayZone::Local => {
+                let secs = t.nanos.div_euclid(NANOS_PER_SEC) as i64;
+                Zone::local().offset_at(secs)
+            }
+            DisplayZone::Fixed(offset) => offset,
+        };
+        t.nanos + i128::from(offset) * NANOS_PER_SEC
+    }
+
+    pub fn format(&self, t: &Timestamp) -> String {
+        let wall = self.wall_nanos(t);
+        let day = wall.div_euclid(NANOS_PER_DAY) as i64;
+        let secs = wall.rem_euclid(NANOS_PER_DAY) / NANOS_PER_SEC;
+        let mut out = String::new();
+        if self.date {
+            out.push_str(&format_day(day));
+            out.push(' ');
+        }
+        out.push_str(&format!(
+            "{:02}:{:02}:{:02}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        ));
+        let digits = self.precision.digits();
+        if digits > 0 {
+            let frac = wall.rem_euclid(NANOS_PER_SEC) / 10i128.pow(9 - digits);
+            out.push_str(&format!(".{frac:0width$}", width = digits as usize));
+        }
+        out
+    }
+
+    /// Days since the epoch of `t`'s date in the display zone.
+    pub fn day(&self, t: &Timestamp) -> i64 {
+        self.wall_nanos(t).div_euclid(NANOS_PER_DAY) as i64
+    }
+
+    /// Signed elapsed seconds, `+12.345`, right-aligned to the width of
+    /// the `HH:MM:SS.mmm` it replaces.
+    pub fn format_elapsed(&self, nanos: i128) -> String {
+        let sign = if nanos < 0 { '-' } else { '+' };
+        let n = nanos.unsigned_abs();
+        let digits = self.precision.digits();
+        let mut text = format!("{sign}{}", n / NANOS_PER_SEC as u128);
+        if digits > 0 {
+            let frac = n % NANOS_PER_SEC as u128 / 10u128.pow(9 - digits);
+            text.push_str(&format!(".{frac:0width$}", width = digits as usize));
+        }
+        let width = 8 + digits as usize + usize::from(digits > 0);
+        format!("{text:>width$}")
+    }
+
+    /// What is shown for a missing or unparseable timestamp: the format
+    /// with every digit replaced by `?`.
+    pub fn placeholder(&self) -> String {
+        let sample = if self.mode == TimeMode::Absolute {
+            let epoch = Timestamp {
+                nanos: 0,
+                offset_secs: Some(0),
+            };
+            TimeFormat {
+                zone: DisplayZone::Utc,
+                ..*self
+            }
+            .format(&epoch)
+        } else {
+            self.format_elapsed(0)
+        };
+        sample
+            .chars()
+            .map(|c| if c.is_ascii_digit() { '?' } else { c })
+            .collect()
+    }
+}
+
+/// `YYYY-MM-DD` of a day number from [`TimeFormat::day`].
+pub fn format_day(day: i64) -> String {
+    let (y, m, d) = civil_from_days(day);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+// ---------- timestamp parsing ----------
+
+/// An instant parsed from a `ts` value: nanoseconds since the Unix epoch,
+/// plus the UTC offset it was written with (`None` if it had none).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub nanos: i128,
+    pub offset_secs: Option<i32>,
+}
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+const NANOS_PER_DAY: i128 = 86_400 * NANOS_PER_SEC;
+
+impl Timestamp {
+    /// Signed nanoseconds from `earlier` to `self`.
+    pub fn since(&self, earlier: &Timestamp) -> i128 {
+        self.nanos - earlier.nanos
+    }
+
+    /// Nanoseconds since the epoch of the wall-clock time in the offset the
+    /// timestamp was written with (UTC without one).
+    pub fn local_nanos(&self) -> i128 {
+        self.nanos + i128::from(self.offset_secs.unwrap_or(0)) * NANOS_PER_SEC
+    }
+
+    /// The current time, in UTC.
+    pub fn now() -> Self {
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        Timestamp {
+            nanos: since_epoch.as_nanos() as i128,
+            offset_secs: Some(0),
+        }
+    }
+}
+
+/// Parse an RFC 3339 / ISO 8601 timestamp such as
+/// `2025-02-15T09:12:01.123456Z` or `2025-02-15 18:12:01+09:00`.
+///
+/// The fraction may have any number of digits (beyond nanoseconds they are
+/// dropped); a missing zone is taken as UTC.
+pub fn parse_rfc3339(ts: &str) -> Option<Timestamp> {
+    let b = ts.as_bytes();
+    if b.len() < 19 {
+        return None;
+    }
+    let num = |r: std::ops::Range<usize>| -> Option<i64> {
+        let s = ts.get(r)?;
+        if !s.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok()
+    };
+
+    let year = num(0..4)?;
+    if b.get(4) != Some(&b'-') || b.get(7) != Some(&b'-') {
+        return None;
+    }
+    let month = num(5..7)?;
+    let day = num(8..10)?;
+    if !matches!(b.get(10), Some(b'T' | b't' | b' ')) {
+        return None;
+    }
+    let hour = num(11..13)?;
+    if b.get(13) != Some(&b':') || b.get(16) != Some(&b':') {
+        return None;
+    }
+    let min = num(14..16)?;
+    let sec = num(17..19)?;
+    if !(1..=12).contains(&month) || hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    let mut i = 19;
+    let mut frac_nanos = 0i64;
+    if b.get(i) == Some(&b'.') || b.get(i) == Some(&b',') {
+        i += 1;
+        let start = i;
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+        }
+        if i == start {
+            return None;
+        }
+        let digits = &ts[start..i.min(start + 9)];
+        frac_nanos = digits.parse::<i64>().ok()? * 10i64.pow(9 - digits.len() as u32);
+    }
+
+    let offset_secs = match b.get(i) {
+        None => None,
+        Some(b'Z' | b'z') if i + 1 == b.len() => Some(0),
+        Some(b'+' | b'-') => Some(parse_offset(&ts[i..])?),
+        _ => return None,
+    };
+
+    let days = days_from_civil(year, month, day);
+    let local_secs = days * 86_400 + hour * 3600 + min * 60 + sec;
+    let utc_secs = local_secs - i64::from(offset_secs.unwrap_or(0));
+    Some(Timestamp {
+        nanos: i128::from(utc_secs) * 1_000_000_000 + i128::from(frac_nanos),
+        offset_secs,
+    })
+}
+
+/// Number of days in `month` (1-12) of `year`, by Gregorian leap years.
+pub(crate) fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's
+/// `days_from_civil`).
+pub(crate) fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The proleptic Gregorian `(year, month, day)` of a day number; inverse of
+/// [`days_from_civil`].
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+/// The year of `secs` since the epoch.
+pub(crate) fn year_of(secs: i64) -> i64 {
+    civil_from_days(secs.div_euclid(86_400)).0
+}
+
+// ---------- numeric timestamps ----------
+
+/// What a numeric `ts` counts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    /// The Unix epoch.
+    Epoch,
+    /// Some arbitrary point such as boot; see [`TimestampParser::anchor`].
+    Monotonic,
+}
+
+/// The shape of `ts` values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    /// RFC 3339, or a number whose clock and unit are guessed from the
+    /// first numeric value (see [`detect_numeric`]).
+    #[default]
+    Auto,
+    Rfc3339,
+    /// A number of seconds, milliseconds... (a fraction is allowed).
+    Numeric(Clock, Precision),
+}
+
+impl std::str::FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numeric = |clock, unit: &str| match unit {
+            "s" => Some(TimestampFormat::Numeric(clock, Precision::Seconds)),
+            "ms" => Some(TimestampFormat::Numeric(clock, Precision::Millis)),
+            "us" | "µs" => Some(TimestampFormat::Numeric(clock, Precision::Micros)),
+            "ns" => Some(TimestampFormat::Numeric(clock, Precision::Nanos)),
+            _ => None,
+        };
+        let parsed = match s {
+            "auto" => Some(TimestampFormat::Auto),
+            "rfc3339" | "iso" => Some(TimestampFormat::Rfc3339),
+            _ => match s.split_once('-') {
+                Some(("epoch", unit)) => numeric(Clock::Epoch, unit),
+                Some(("mono", unit)) => numeric(Clock::Monotonic, unit),
+                _ => None,
+            },
+        };
+        parsed.ok_or_else(|| {
+            format!(
+                "expected auto, rfc3339, epoch-UNIT or mono-UNIT (UNIT: s, ms, us or ns), \
+                 got `{s}`"
+            )
+        })
+    }
+}
+
+/// `[VALUE=]TIME`: the monotonic reading VALUE (in the log's own unit, 0
+/// when left out) was taken at the wall-clock TIME.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anchor {
+    pub value: String,
+    pub at: Timestamp,
+}
+
+impl std::str::FromStr for Anchor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, at) = s.split_once('=').unwrap_or(("0", s));
+        let err = || format!("expected [VALUE=]TIME with an RFC 3339 TIME, got `{s}`");
+        if split_number(value).is_none() {
+            return Err(err());
+        }
+        Ok(Anchor {
+            value: value.to_string(),
+            at: parse_rfc3339(at).ok_or_else(err)?,
+        })
+    }
+}
+
+/// Reads the `ts` values of one stream: RFC 3339 (see [`parse_rfc3339`])
+/// or numbers like `1739612345.123456`, as `format` says.
+///
+/// The clock and unit of numeric values are settled on the first one and
+/// shared by every clone, so everything reading one stream (filters,
+/// renderer, call timing) must use clones of one parser, and every other
+/// stream a [`TimestampParser::fresh`] one.
+#[derive(Debug, Clone, Default)]
+pub struct TimestampParser {
+    pub format: TimestampFormat,
+    /// Maps monotonic values onto wall time; without it they count from
+    /// the epoch, so times of day read as time since the clock's start.
+    pub anchor: Option<Anchor>,
+    numeric: Arc<OnceLock<Numeric>>,
+}
+
+/// The numeric format in use, once known.
+#[derive(Debug, Clone, Copy)]
+struct Numeric {
+    unit: Precision,
+    /// Added to the value, in nanoseconds.
+    shift: i128,
+}
+
+impl TimestampParser {
+    /// A parser with the same settings that has not seen any value yet.
+    pub fn fresh(&self) -> Self {
+        TimestampParser {
+            format: self.format,
+            anchor: self.anchor.clone(),
+            numeric: Arc::default(),
+        }
+    }
+
+    /// Parse a `ts` value. Numeric values are shown in UTC.
+    pub fn parse(&self, ts: &str) -> Option<Timestamp> {
+        if self.format != TimestampFormat::Rfc3339
+            && let Some((int, frac)) = split_number(ts)
+        {
+            let numeric = match self.numeric.get() {
+                Some(n) => *n,
+                None => *self
+                    .numeric
+                    .get_or_init(|| self.resolve(int, frac.is_some())),
+            };
+            let nanos = numeric_nanos(int, frac, numeric.unit)?;
+            return Some(Timestamp {
+                nanos: nanos.checked_add(numeric.shift)?,
+                offset_secs: Some(0),
+            });
+        }
+        match self.format {
+            TimestampFormat::Numeric(..) => None,
+            _ => parse_rfc3339(ts),
+        }
+    }
+
+    /// The numeric format, from the first numeric value seen.
+    fn resolve(&self, int: &str, has_frac: bool) -> Numeric {
+        let (clock, unit) = match self.format {
+            TimestampFormat::Numeric(clock, unit) => (clock, unit),
+            _ => detect_numeric(int, has_frac),
+        };
+        let shift = match (&self.anchor, clock) {
+            (Some(anchor), Clock::Monotonic) => split_number(&anchor.value)
+                .and_then(|(int, frac)| numeric_nanos(int, frac, unit))
+                .map_or(0, |value| anchor.at.nanos - value),
+            _ => 0,
+        };
+        Numeric { unit, shift }
+    }
+}
+
+/// Guess clock and unit from the integer digits of a value: an epoch time
+/// if it falls in 2000..2100 as seconds, milliseconds, microseconds or
+/// nanoseconds, else a monotonic counter, in seconds when written with a
+/// fraction and in nanoseconds when not.
+pub fn detect_numeric(int: &str, has_frac: bool) -> (Clock, Precision) {
+    const FROM: i128 = 946_684_800; // 2000-01-01
+    const TO: i128 = 4_102_444_800; // 2100-01-01
+    if let Ok(value) = int.parse::<i128>() {
+        for unit in [
+            Precision::Seconds,
+            Precision::Millis,
+            Precision::Micros,
+            Precision::Nanos,
+        ] {
+            let scale = 10i128.pow(unit.digits());
+            if (FROM * scale..TO * scale).contains(&value) {
+                return (Clock::Epoch, unit);
+            }
+        }
+    }
+    if has_frac {
+        (Clock::Monotonic, Precision::Seconds)
+    } else {
+        (Clock::Monotonic, Precision::Nanos)
+    }
+}
+
+/// `123` or `123.456` as integer and fraction digits.
+fn split_number(s: &str) -> Option<(&str, Option<&str>)> {
+    let (int, frac) = match s.split_once('.') {
+        Some((int, frac)) => (int, Some(frac)),
+        None => (s, None),
+    };
+    let digits = |p: &str| !p.is_empty() && p.len() <= 30 && p.bytes().all(|c| c.is_ascii_digit());
+    (digits(int) && frac.is_none_or(digits)).then_some((int, frac))
+}
+
+/// A value in `unit` as nanoseconds; fraction digits below a nanosecond
+/// are dropped.
+fn numeric_nanos(int: &str, frac: Option<&str>, unit: Precision) -> Option<i128> {
+    let frac_digits = (9 - unit.digits()) as usize;
+    let mut nanos = int
+        .parse::<i128>()
+        .ok()?
+        .checked_mul(10i128.pow(frac_digits as u32))?;
+    if let Some(frac) = frac {
+        let kept = &frac[..frac.len().min(frac_digits)];
+        if !kept.is_empty() {
+            let scale = 10i128.pow((frac_digits - kept.len()) as u32);
+            nanos += kept.parse::<i128>().ok()? * scale;
+        }
+    }
+    Some(nanos)
+}
+
+// ---------- durations ----------
+
+/// Format nanoseconds compactly: `850ns`, `12.3µs`, `12.4ms`, `1.234s`,
+/// `2m03.5s`.
+pub fn format_duration(nanos: i128) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let n = nanos.unsigned_abs();
+    // Each unit is used while its rounded value stays below the next one,
+    // so there is no `1000.0ms` or `1m60.0s`.
+    let s = if n < 1_000 {
+        format!("{n}ns")
+    } else if n < 999_950 {
+        format!("{:.1}µs", n as f64 / 1e3)
+    } else if n < 999_950_000 {
+        format!("{:.1}ms", n as f64 / 1e6)
+    } else if n < 59_999_500_000 {
+        format!("{:.3}s", n as f64 / 1e9)
+    } else {
+        let tenths = (n + 50_000_000) / 100_000_000;
+        let (m, rest) = (tenths / 600, tenths % 600);
+        format!("{m}m{:02}.{}s", rest / 10, rest % 10)
+    };
+    format!("{sign}{s}")
+}
+
+/// Parse a duration like `250ms`, `1.5s`, `40us`/`40µs`, `100ns`, `2m` or
+/// `1h` into nanoseconds. A bare number is seconds.
+pub fn parse_duration(s: &str) -> Result<i128, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let value: f64 = num
+        .parse()
+        .map_err(|_| format!("expected a duration like 250ms or 1.5s, got `{s}`"))?;
+    let scale = match unit {
+        "ns" => 1.0,
+        "us" | "µs" => 1e3,
+        "ms" => 1e6,
+        "" | "s" => 1e9,
+        "m" | "min" => 60e9,
+        "h" => 3600e9,
+        other => return Err(format!("unknown duration unit `{other}`")),
+    };
+    Ok((value * scale).round() as i128)
+}
+
+// ---------- time ranges ----------
+
+/// One end of a `--since` / `--until` range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    /// `2025-02-15T14:03:10Z`, `2025-02-15 14:03:10+09:00`, or a bare date
+    /// `2025-02-15` (midnight UTC).
+    At(Timestamp),
+    /// `14:03:10`, `14:03:10.250` or `14:03`: that time on the day of the
+    /// first timestamped record, in its offset. Nanoseconds since midnight.
+    TimeOfDay(i128),
+    /// `+30s`: that long after the first timestamped record.
+    AfterFirst(i128),
+    /// `-10m`: that long before now.
+    Ago(i128),
+}
+
+impl TimeBound {
+    /// The instant this bound stands for, given the first timestamp of the
+    /// input.
+    pub fn resolve(&self, first: &Timestamp) -> Timestamp {
+        let nanos = match *self {
+            TimeBound::At(t) => return t,
+            TimeBound::TimeOfDay(tod) => {
+                let offset = i128::from(first.offset_secs.unwrap_or(0)) * NANOS_PER_SEC;
+                let midnight = first.local_nanos().div_euclid(NANOS_PER_DAY) * NANOS_PER_DAY;
+                midnight + tod - offset
+            }
+            TimeBound::AfterFirst(d) => first.nanos + d,
+            TimeBound::Ago(d) => Timestamp::now().nanos - d,
+        };
+        Timestamp {
+            nanos,
+            offset_secs: first.offset_secs,
+        }
+    }
+}
+
+impl std::str::FromStr for TimeBound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(d) = s.strip_prefix('+') {
+            return parse_duration(d).map(TimeBound::AfterFirst);
+        }
+        if let Some(d) = s.strip_prefix('-') {
+            return parse_duration(d).map(TimeBound::Ago);
+        }
+        if let Some(t) = parse_rfc3339(s) {
+            return Ok(TimeBound::At(t));
+        }
+        if s.len() == 10
+            && let Some(t) = parse_rfc3339(&format!("{s}T00:00:00Z"))
+        {
+            return Ok(TimeBound::At(t));
+        }
+        parse_time_of_day(s)
+            .map(TimeBound::TimeOfDay)
+            .ok_or_else(|| {
+                format!(
+                    "expected an RFC 3339 timestamp, a date, a time of day (14:03:10), \
+                 +DURATION or -DURATION, got `{s}`"
+                )
+            })
+    }
+}
+
+/// `HH:MM`, `HH:MM:SS` or `HH:MM:SS.fff` as nanoseconds since midnight.
+fn parse_time_of_day(s: &str) -> Option<i128> {
+    let (hms, frac) = match s.split_once(['.', ',']) {
+        Some((hms, frac)) => (hms, Some(frac)),
+        None => (s, None),
+    };
+    let parts: Vec<&str> = hms.split(':').collect();
+    let field = |p: &str, max: i128| -> Option<i128> {
+        if p.len() != 2 || !p.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let v: i128 = p.parse().ok()?;
+        (v <= max).then_some(v)
+    };
+    let (h, m, sec) = match parts[..] {
+        [h, m] if frac.is_none() => (field(h, 23)?, field(m, 59)?, 0),
+        [h, m, sec] => (field(h, 23)?, field(m, 59)?, field(sec, 60)?),
+        _ => return None,
+    };
+    let mut nanos = 0;
+    if let Some(frac) = frac {
+        if frac.is_empty() || !frac.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let digits = &frac[..frac.len().min(9)];
+        nanos = digits.parse::<i128>().ok()? * 10i128.pow(9 - digits.len() as u32);
+    }
+    Some(((h * 60 + m) * 60 + sec) * NANOS_PER_SEC + nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(ts: &str) -> Option<i128> {
+        parse_rfc3339(ts).map(|t| t.nanos)
+    }
+
+    #[test]
+    fn rfc3339() {
+        assert_eq!(utc("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(utc("1970-01-01T00:00:01.5Z"), Some(1_500_000_000));
+        assert_eq!(utc("1970-01-01 01:00:00+01:00"), Some(0));
+        assert_eq!(utc("1970-01-01t00:00:00,000000001z"), Some(1));
+        let t = parse_rfc3339("2025-02-15T09:12:01.100-05:30").unwrap();
+        assert_eq!(t.offset_secs, Some(-19_800));
+        assert_eq!(
+            parse_rfc3339("2025-02-15T09:12:01").unwrap().offset_secs,
+            None
+        );
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse_offset("+09:00"), Some(32_400));
+        assert_eq!(parse_offset("-0530"), Some(-19_800));
+        assert_eq!(parse_offset("+09"), Some(32_400));
+        assert_eq!(parse_offset("-00:00"), Some(0));
+        assert_eq!(parse_offset("+24:00"), None);
+        assert_eq!(parse_offset("+09:60"), None);
+        assert_eq!(parse_offset("09:00"), None);
+        assert_eq!(parse_offset("+9:00"), None);
+        assert_eq!(parse_offset("+09:0:"), None);
+        assert_eq!(parse_offset("+"), None);
+        assert_eq!(parse_offset(""), None);
+    }
+
+    #[test]
+    fn non_ascii_offsets_are_rejected() {
+        assert_eq!(parse_offset("+aé1"), None);
+        assert_eq!(parse_offset("+é:00"), None);
+        assert_eq!(parse_offset("+0€"), None);
+        assert_eq!(parse_offset("-😀"), None);
+        assert!("+aé1".parse::<DisplayZone>().is_err());
+        assert!(utc("2025-01-01T00:00:00+aé1").is_none());
+        assert!("2025-01-01T00:00:00+aé1".parse::<TimeBound>().is_err());
+    }
+
+    #[test]
+    fn rfc3339_rejects_impossible_dates() {
+        assert!(utc("2025-02-31T00:00:00Z").is_none());
+        assert!(utc("2025-02-29T00:00:00Z").is_none());
+        assert!(utc("2024-02-29T00:00:00Z").is_some());
+        assert!(utc("1900-02-29T00:00:00Z").is_none());
+        assert!(utc("2000-02-29T00:00:00Z").is_some());
+        assert!(utc("2025-04-31T00:00:00Z").is_none());
+        assert!(utc("2025-12-31T23:59:60Z").is_some());
+        assert!(utc("2025-13-01T00:00:00Z").is_none());
+        assert!(utc("2025-01-00T00:00:00Z").is_none());
+        assert!(utc("2025-01-01T24:00:00Z").is_none());
+        assert!(utc("2025-01-01T00:00:00.Z").is_none());
+        assert!(utc("2025-01-01T00:00:00Zx").is_none());
+    }
+
+    #[test]
+    fn numeric_detection() {
+        use {Clock::*, Precision::*};
+        assert_eq!(detect_numeric("1739612345", false), (Epoch, Seconds));
+        assert_eq!(detect_numeric("1739612345", true), (Epoch, Seconds));
+        assert_eq!(detect_numeric("1739612345123", false), (Epoch, Millis));
+        assert_eq!(detect_numeric("1739612345123456", false), (Epoch, Micros));
+        assert_eq!(detect_numeric("1739612345123456789", false), (Epoch, Nanos));
+        assert_eq!(detect_numeric("123456789012", false), (Monotonic, Nanos));
+        assert_eq!(detect_numeric("12345", true), (Monotonic, Seconds));
+        assert_eq!(detect_numeric("946684799", false), (Monotonic, Nanos));
+        assert_eq!(detect_numeric("4102444800", true), (Monotonic, Seconds));
+    }
+
+    fn parser(format: &str) -> TimestampParser {
+        TimestampParser {
+            format: format.parse().unwrap(),
+            ..TimestampParser::default()
+        }
+    }
+
+    #[test]
+    fn numeric_timestamps() {
+        let p = parser("auto");
+        let t = p.parse("1739612345.5").unwrap();
+        assert_eq!(t.nanos, 1_739_612_345_500_000_000);
+        assert_eq!(t.offset_secs, Some(0));
+        assert_eq!(
+            p.parse("2025-02-15T09:39:05Z").unwrap().nanos,
+            1_739_612_345 * NANOS_PER_SEC
+        );
+        let p = parser("epoch-ms");
+        assert_eq!(
+            p.parse("1739612345123.4567").unwrap().nanos,
+            1_739_612_345_123_456_700
+        );
+        assert_eq!(p.parse("2025-02-15T09:39:05Z"), None);
+        assert_eq!(parser("rfc3339").parse("1739612345"), None);
+        assert_eq!(p.parse("12a"), None);
+        assert_eq!(p.parse("1.2.3"), None);
+        assert_eq!(p.parse(&"9".repeat(31)), None);
+        assert_eq!(parser("mono-s").parse(&"9".repeat(30)), None);
+    }
+
+    #[test]
+    fn the_first_numeric_value_settles_the_unit() {
+        let p = TimestampParser::default();
+        let clone = p.clone();
+        assert_eq!(
+            p.parse("1739612345123").unwrap().nanos,
+            1_739_612_345_123_000_000
+        );
+        // Later values keep the unit, whatever their size, in every clone.
+        assert_eq!(
+            clone.parse("1739612345").unwrap().nanos,
+            1_739_612_345_000_000
+        );
+        // A fresh parser decides for itself.
+        let fresh = p.fresh();
+        assert_eq!(
+            fresh.parse("1739612345").unwrap().nanos,
+            1_739_612_345 * NANOS_PER_SEC
+        );
+        assert_eq!(p.parse("1739612345").unwrap().nanos, 1_739_612_345_000_000);
+    }
+
+    #[test]
+    fn anchored_monotonic_timestamps() {
+        let p = TimestampParser {
+            anchor: Some("100=2025-01-01T00:00:00Z".parse().unwrap()),
+            ..parser("mono-s")
+        };
+        let at = parse_rfc3339("2025-01-01T00:00:00Z").unwrap().nanos;
+        assert_eq!(p.parse("100").unwrap().nanos, at);
+        assert_eq!(p.parse("101.25").unwrap().nanos, at + 1_250_000_000);
+        // Without an anchor, monotonic values count from the epoch.
+        assert_eq!(
+            parser("mono-s").parse("5").unwrap().nanos,
+            5 * NANOS_PER_SEC
+        );
+        assert!("x=2025-01-01T00:00:00Z".parse::<Anchor>().is_err());
+        assert!("100=yesterday".parse::<Anchor>().is_err());
+    }
+
+    #[test]
+    fn timestamp_format_names() {
+        assert_eq!("iso".parse(), Ok(TimestampFormat::Rfc3339));
+        assert_eq!(
+            "mono-µs".parse(),
+            Ok(TimestampFormat::Numeric(
+                Clock::Monotonic,
+                Precision::Micros
+            ))
+        );
+        assert!("epoch".parse::<TimestampFormat>().is_err());
+        assert!("epoch-h".parse::<TimestampFormat>().is_err());
+    }
+
+    #[test]
+    fn durations_round_into_the_next_unit() {
+        assert_eq!(format_duration(999), "999ns");
+        assert_eq!(format_duration(999_949), "999.9µs");
+        assert_eq!(format_duration(999_950), "1.0ms");
+        assert_eq!(format_duration(999_999_999), "1.000s");
+        assert_eq!(format_duration(59_999_499_999), "59.999s");
+        assert_eq!(format_duration(59_999_999_999), "1m00.0s");
+        assert_eq!(format_duration(119_960_000_000), "2m00.0s");
+        assert_eq!(format_duration(125_400_000_000), "2m05.4s");
+        assert_eq!(format_duration(-1_500_000), "-1.5ms");
+    }
+
+    #[test]
+    fn duration_parsing() {
+        assert_eq!(parse_duration("250ms"), Ok(250_000_000));
+        assert_eq!(parse_duration("1.5s"), Ok(1_500_000_000));
+        assert_eq!(parse_duration("40µs"), Ok(40_000));
+        assert_eq!(parse_duration("2"), Ok(2_000_000_000));
+        assert!(parse_duration("fast").is_err());
+    }
+}
@@ -0,0 +1,255 @@
+// ---------- per-call elapsed time ----------
+//
+// Streaming counterpart of `CallTree`: tracks the open invocations with the
+// same rules, but keeps only timestamps and counters, and reports each call
+// as soon as the record that ends it arrives.
+
+use crate::calltree::closes;
+use crate::record::Record;
+use crate::time::{Timestamp, TimestampParser};
+
+/// Elapsed time of one finished invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallTiming {
+    pub func: Option<String>,
+    pub depth: usize,
+    /// Records in the whole subtree.
+    pub records: usize,
+    /// Whether the invocation had nested calls.
+    pub has_children: bool,
+    /// Entry to the last record of the subtree, in nanoseconds; `None`
+    /// without parseable timestamps.
+    pub inclusive: Option<i128>,
+    /// `inclusive` minus the inclusive time of the direct children.
+    pub exclusive: Option<i128>,
+    /// Whether a record of the invocation itself was shown, i.e. not only
+    /// passed to [`CallTimer::push_hidden`].
+    pub shown: bool,
+}
+
+#[derive(Debug)]
+struct Open {
+    func: Option<String>,
+    depth: usize,
+    first: Option<Timestamp>,
+    last: Option<Timestamp>,
+    records: usize,
+    has_children: bool,
+    children_inclusive: i128,
+    shown: bool,
+}
+
+/// Tracks open invocations and times them when they return.
+#[derive(Debug, Default)]
+pub struct CallTimer {
+    timestamps: TimestampParser,
+    open: Vec<Open>,
+}
+
+impl CallTimer {
+    pub fn new(timestamps: TimestampParser) -> Self {
+        CallTimer {
+            timestamps,
+            open: Vec::new(),
+        }
+    }
+
+    /// Account for the next record. Returns the invocations it ends,
+    /// innermost first.
+    pub fn push(&mut self, rec: &Record) -> Vec<CallTiming> {
+        self.account(rec, true)
+    }
+
+    /// Like [`CallTimer::push`], for a record that is not shown.
+    pub fn push_hidden(&mut self, rec: &Record) -> Vec<CallTiming> {
+        self.account(rec, false)
+    }
+
+    fn account(&mut self, rec: &Record, shown: bool) -> Vec<CallTiming> {
+        let mut done = Vec::new();
+        while let Some(top) = self.open.last() {
+            if !closes(top.depth, top.func.as_deref(), rec) {
+                break;
+            }
+            done.push(self.pop());
+        }
+
+        let ts = rec.ts.as_deref().and_then(|t| self.timestamps.parse(t));
+        match self.open.last_mut() {
+            Some(top) if top.depth == rec.depth => {
+                top.records += 1;
+                top.shown |= shown;
+                top.first = top.first.or(ts);
+                top.last = ts.or(top.last);
+            }
+            parent => {
+                if let Some(p) = parent {
+                    p.has_children = true;
+                }
+                self.open.push(Open {
+                    func: rec.func.clone(),
+                    depth: rec.depth,
+                    first: ts,
+                    last: ts,
+                    records: 1,
+                    has_children: false,
+                    children_inclusive: 0,
+                    shown,
+                });
+            }
+        }
+        done
+    }
+
+    /// When the innermost open invocation was entered (its first
+    /// timestamp).
+    pub fn entry(&self) -> Option<Timestamp> {
+        self.open.last().and_then(|o| o.first)
+    }
+
+    /// End of input: every open invocation returns, innermost first.
+    pub fn finish(&mut self) -> Vec<CallTiming> {
+        let mut done = Vec::new();
+        while !self.open.is_empty() {
+            done.push(self.pop());
+        }
+        done
+    }
+
+    /// Close the innermost invocation and fold it into its parent.
+    fn pop(&mut self) -> CallTiming {
+        let o = self.open.pop().expect("pop on empty call stack");
+        let inclusive = match (o.first, o.last) {
+            (Some(first), Some(last)) => Some(last.since(&first)),
+            _ => None,
+        };
+        if let Some(parent) = self.open.last_mut() {
+            parent.records += o.records;
+            parent.last = match (parent.last, o.last) {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+            parent.children_inclusive += inclusive.unwrap_or(0);
+        }
+        CallTiming {
+            func: o.func,
+            depth: o.depth,
+            records: o.records,
+            has_children: o.has_children,
+            inclusive,
+            exclusive: inclusive.map(|i| i - o.children_inclusive),
+            shown: o.shown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A record at `depth` in `func`, `ms` milliseconds into the log, or
+    /// without a timestamp.
+    fn rec(depth: usize, func: &str, ms: Option<u32>) -> Record {
+        let ts = ms.map_or(String::new(), |ms| {
+            format!("ts=2025-01-01T00:00:{:02}.{:03}Z ", ms / 1000, ms % 1000)
+        });
+        Record::parse(&format!("{ts}depth={depth} func={func}")).unwrap()
+    }
+
+    /// `func inclusive/exclusive records`, in milliseconds.
+    fn summary(timings: &[CallTiming]) -> Vec<String> {
+        let ms = |n: Option<i128>| n.map_or("-".to_string(), |n| (n / 1_000_000).to_string());
+        timings
+            .iter()
+            .map(|t| {
+                format!(
+                    "{} {}/{} {}",
+                    t.func.as_deref().unwrap_or("?"),
+                    ms(t.inclusive),
+                    ms(t.exclusive),
+                    t.records
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_calls_split_total_and_self_time() {
+        let mut timer = CallTimer::default();
+        assert!(timer.push(&rec(0, "main", Some(0))).is_empty());
+        assert!(timer.push(&rec(1, "f", Some(10))).is_empty());
+        assert!(timer.push(&rec(2, "g", Some(20))).is_empty());
+        assert_eq!(
+            timer.entry(),
+            Some(timer.timestamps.parse("2025-01-01T00:00:00.020Z").unwrap())
+        );
+        assert!(timer.push(&rec(2, "g", Some(50))).is_empty());
+        // Back at depth 1 in `f`: `g` returns.
+        assert_eq!(summary(&timer.push(&rec(1, "f", Some(60)))), ["g 30/30 2"]);
+        // Back at depth 0: `f` returns, with `g` nested in it.
+        let done = timer.push(&rec(0, "main", Some(100)));
+        assert_eq!(summary(&done), ["f 50/20 4"]);
+        assert!(done[0].has_children);
+        assert_eq!(summary(&timer.finish()), ["main 100/50 6"]);
+    }
+
+    #[test]
+    fn one_record_closes_several_calls_innermost_first() {
+        let mut timer = CallTimer::default();
+        for (depth, func, ms) in [(0, "main", 0), (1, "a", 5), (2, "b", 7), (3, "c", 9)] {
+            timer.push(&rec(depth, func, Some(ms)));
+        }
+        let done = timer.push(&rec(0, "end", Some(12)));
+        assert_eq!(
+            summary(&done),
+            ["c 0/0 1", "b 2/2 2", "a 4/2 3", "main 9/5 4"]
+        );
+        assert!(!done[0].has_children);
+    }
+
+    #[test]
+    fn unclosed_calls_return_at_end_of_input() {
+        let mut timer = CallTimer::default();
+        timer.push(&rec(0, "main", Some(0)));
+        timer.push(&rec(1, "f", Some(10)));
+        timer.push(&rec(2, "g", Some(15)));
+        assert_eq!(
+            summary(&timer.finish()),
+            ["g 0/0 1", "f 5/5 2", "main 15/10 3"]
+        );
+        assert!(timer.finish().is_empty());
+        assert_eq!(timer.entry(), None);
+    }
+
+    #[test]
+    fn records_without_timestamps() {
+        let mut timer = CallTimer::default();
+        timer.push(&rec(0, "main", Some(0)));
+        // Entered without a timestamp: the first one that comes counts.
+        timer.push(&rec(1, "f", None));
+        timer.push(&rec(1, "f", Some(20)));
+        timer.push(&rec(1, "f", Some(30)));
+        // Never timed at all.
+        assert_eq!(summary(&timer.push(&rec(1, "g", None))), ["f 10/10 3"]);
+        timer.push(&rec(2, "h", None));
+        let done = timer.push(&rec(0, "main", None));
+        assert_eq!(summary(&done), ["h -/- 1", "g -/- 2"]);
+        assert_eq!(summary(&timer.finish()), ["main 30/20 7"]);
+    }
+
+    #[test]
+    fn hidden_records_time_calls_without_showing_them() {
+        let mut timer = CallTimer::default();
+        timer.push(&rec(0, "main", Some(0)));
+        timer.push_hidden(&rec(1, "f", Some(10)));
+        timer.push_hidden(&rec(2, "g", Some(20)));
+        let done = timer.push(&rec(1, "f", Some(40)));
+        assert_eq!(summary(&done), ["g 0/0 1"]);
+        assert!(!done[0].shown);
+        // Shown once a record of its own was.
+        let done = timer.push(&rec(0, "main", Some(50)));
+        assert_eq!(summary(&done), ["f 30/30 3"]);
+        assert!(done[0].shown);
+        assert_eq!(summary(&timer.finish()), ["main 50/20 5"]);
+    }
+}
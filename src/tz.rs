@@ -0,0 +1,490 @@
+// ---------- local time zone ----------
+//
+// The local UTC offset without libc: read the TZif file named by `TZ` (or
+// /etc/localtime), and for instants past its last transition apply the
+// POSIX TZ rule from its footer (RFC 8536). `TZ` may also hold such a rule
+// directly (`CET-1CEST,M3.5.0,M10.5.0/3`). Anything unreadable means UTC.
+
+use std::sync::OnceLock;
+
+use crate::time::days_from_civil;
+
+/// UTC offsets of one time zone over time.
+#[derive(Debug, Clone, Default)]
+pub struct Zone {
+    /// Transition instants, seconds since the epoch, ascending.
+    transitions: Vec<i64>,
+    /// For each transition, the index into `offsets` that starts there.
+    kinds: Vec<u8>,
+    /// UTC offsets in seconds; the first applies before any transition.
+    offsets: Vec<i32>,
+    /// Applies after the last transition.
+    rule: Option<Rule>,
+}
+
+impl Zone {
+    pub fn utc() -> Self {
+        Zone::default()
+    }
+
+    /// The local zone, loaded once.
+    pub fn local() -> &'static Zone {
+        static LOCAL: OnceLock<Zone> = OnceLock::new();
+        LOCAL.get_or_init(Zone::load_local)
+    }
+
+    fn load_local() -> Zone {
+        let tz = std::env::var("TZ").ok();
+        let name = match tz.as_deref() {
+            None => "/etc/localtime",
+            Some("") => return Zone::utc(),
+            Some(tz) => tz.strip_prefix(':').unwrap_or(tz),
+        };
+        let path = if name.starts_with('/') {
+            name.to_string()
+        } else {
+            format!("/usr/share/zoneinfo/{name}")
+        };
+        if let Some(zone) = std::fs::read(path).ok().and_then(|d| Zone::from_tzif(&d)) {
+            return zone;
+        }
+        match Rule::parse(name) {
+            Some(rule) => Zone {
+                rule: Some(rule),
+                ..Zone::default()
+            },
+            None => Zone::utc(),
+        }
+    }
+
+    /// Parse the contents of a TZif file.
+    pub fn from_tzif(data: &[u8]) -> Option<Zone> {
+        let header = TzifHeader::parse(data)?;
+        let (header, body, time_size) = if header.version >= b'2' {
+            let rest = data.get(header.block_len(4)..)?;
+            (TzifHeader::parse(rest)?, &rest[44..], 8)
+        } else {
+            (header, &data[44..], 4)
+        };
+
+        let mut pos = 0;
+        let mut take = |len: usize| -> Option<&[u8]> {
+            let bytes = body.get(pos..pos + len)?;
+            pos += len;
+            Some(bytes)
+        };
+        let times = take(header.timecnt * time_size)?;
+        let kinds = take(header.timecnt)?.to_vec();
+        let types = take(header.typecnt * 6)?;
+        let transitions = times
+            .chunks(time_size)
+            .map(|c| match time_size {
+                8 => i64::from_be_bytes(c.try_into().unwrap_or_default()),
+                _ => i64::from(i32::from_be_bytes(c.try_into().unwrap_or_default())),
+            })
+            .collect();
+        let offsets: Vec<i32> = types
+            .chunks(6)
+            .map(|c| i32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        if offsets.is_empty() || kinds.iter().any(|&k| usize::from(k) >= offsets.len()) {
+            return None;
+        }
+
+        let rule = if time_size == 8 {
+            let rest = body.get(header.block_len(8) - 44..)?;
+            let footer = std::str::from_utf8(rest).ok()?;
+            Rule::parse(footer.trim_matches('\n'))
+        } else {
+            None
+        };
+        Some(Zone {
+            transitions,
+            kinds,
+            offsets,
+            rule,
+        })
+    }
+
+    /// The UTC offset in seconds at `secs` since the epoch.
+    pub fn offset_at(&self, secs: i64) -> i32 {
+        let i = self.transitions.partition_point(|&t| t <= secs);
+        if i == self.transitions.len()
+            && let Some(rule) = &self.rule
+        {
+            return rule.offset_at(secs);
+        }
+        match i.checked_sub(1) {
+            Some(last) => self.offsets[usize::from(self.kinds[last])],
+            None => self.offsets.first().copied().unwrap_or(0),
+        }
+    }
+}
+
+/// The counts at the start of a TZif data block.
+struct TzifHeader {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl TzifHeader {
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != b"TZif" {
+            return None;
+        }
+        let count = |i: usize| -> Option<usize> {
+            let b = data.get(20 + i * 4..24 + i * 4)?;
+            Some(u32::from_be_bytes(b.try_into().ok()?) as usize)
+        };
+        Some(TzifHeader {
+            version: data[4],
+            isutcnt: count(0)?,
+            isstdcnt: count(1)?,
+            leapcnt: count(2)?,
+            timecnt: count(3)?,
+            typecnt: count(4)?,
+            charcnt: count(5)?,
+        })
+    }
+
+    /// Length of the header plus its data block, for `time_size`-byte
+    /// transition times.
+    fn block_len(&self, time_size: usize) -> usize {
+        44 + self.timecnt * time_size
+            + self.timecnt
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+// ---------- POSIX TZ rules ----------
+
+/// `std offset [dst [offset] [,start[/time],end[/time]]]`, e.g.
+/// `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Debug, Clone, Copy)]
+struct Rule {
+    /// UTC offset of standard time, in seconds (east positive).
+    std: i32,
+    /// Daylight saving time, if the zone has it.
+    dst: Option<Dst>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Dst {
+    offset: i32,
+    start: (Date, i32),
+    end: (Date, i32),
+}
+
+/// A day of the year in a POSIX rule.
+#[derive(Debug, Clone, Copy)]
+enum Date {
+    /// `Jn`: 1..=365, February 29 never counted.
+    Julian(i64),
+    /// `n`: 0..=365, February 29 counted in leap years.
+    Zero(i64),
+    /// `Mm.w.d`: day `d` (0 = Sunday) of week `w` (5 = last) of month `m`.
+    Month(i64, i64, i64),
+}
+
+impl Rule {
+    fn parse(s: &str) -> Option<Rule> {
+        let mut p = RuleParser { s, pos: 0 };
+        p.name()?;
+        let std = -p.offset()?;
+        if p.done() {
+            return Some(Rule { std, dst: None });
+        }
+        p.name()?;
+        let offset = if p.peek().is_some_and(|c| c != ',') {
+            -p.offset()?
+        } else {
+            std + 3600
+        };
+        // Without explicit dates, the US rules are the POSIX default.
+        let (start, end) = if p.eat(',') {
+            let start = p.date_time()?;
+            if !p.eat(',') {
+                return None;
+            }
+            (start, p.date_time()?)
+        } else {
+            ((Date::Month(3, 2, 0), 7200), (Date::Month(11, 1, 0), 7200))
+        };
+        p.done().then_some(Rule {
+            std,
+            dst: Some(Dst { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, secs: i64) -> i32 {
+        let Some(dst) = self.dst else {
+            return self.std;
+        };
+        let year = crate::time::year_of(secs.saturating_add(i64::from(self.std)));
+        // Transitions happen at local time: start in standard time, end in
+        // daylight saving time. In i128, as far-off years overflow i64.
+        let at = |(date, time): (Date, i32), offset: i32| {
+            i128::from(date.day(year)) * 86_400 + i128::from(time) - i128::from(offset)
+        };
+        let start = at(dst.start, self.std);
+        let end = at(dst.end, dst.offset);
+        let secs = i128::from(secs);
+        let in_dst = if start < end {
+            start <= secs && secs < end
+        } else {
+            !(end <= secs && secs < start)
+        };
+        if in_dst { dst.offset } else { self.std }
+    }
+}
+
+impl Date {
+    /// Days since the epoch of this date in `year`.
+    fn day(&self, year: i64) -> i64 {
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let jan1 = days_from_civil(year, 1, 1);
+        match *self {
+            Date::Julian(n) => jan1 + n - 1 + i64::from(leap && n >= 60),
+            Date::Zero(n) => jan1 + n,
+            Date::Month(m, w, d) => {
+                let first = days_from_civil(year, m, 1);
+                // 1970-01-01 was a Thursday.
+                let weekday = (first + 4).rem_euclid(7);
+                let mut day = first + (d - weekday).rem_euclid(7) + (w - 1) * 7;
+                let next_month = if m == 12 {
+                    days_from_civil(year + 1, 1, 1)
+                } else {
+                    days_from_civil(year, m + 1, 1)
+                };
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            }
+        }
+    }
+}
+
+struct RuleParser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl RuleParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.pos == self.s.len()
+    }
+
+    /// A zone abbreviation: three or more letters, or anything in `<>`.
+    fn name(&mut self) -> Option<()> {
+        let rest = &self.s[self.pos..];
+        let len = if let Some(quoted) = rest.strip_prefix('<') {
+            quoted.find('>')? + 2
+        } else {
+            rest.find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len())
+        };
+        if len < 3 {
+            return None;
+        }
+        self.pos += len;
+        Some(())
+    }
+
+    fn number(&mut self) -> Option<i64> {
+        let rest = &self.s[self.pos..];
+        let len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        self.pos += len;
+        rest[..len].parse().ok()
+    }
+
+    /// `[+-]hh[:mm[:ss]]` in seconds, with the sign as written.
+    fn offset(&mut self) -> Option<i32> {
+        let sign = if self.eat('-') {
+            -1
+        } else {
+            self.eat('+');
+            1
+        };
+        let mut secs = self.number()?.checked_mul(3600)?;
+        if self.eat(':') {
+            secs = secs.checked_add(self.number()?.checked_mul(60)?)?;
+            if self.eat(':') {
+                secs = secs.checked_add(self.number()?)?;
+            }
+        }
+        i32::try_from(sign * secs).ok()
+    }
+
+    /// A date with an optional `/time` (default 02:00).
+    fn date_time(&mut self) -> Option<(Date, i32)> {
+        let date = if self.eat('M') {
+            let m = self.number()?;
+            self.eat('.').then_some(())?;
+            let w = self.number()?;
+            self.eat('.').then_some(())?;
+            let d = self.number()?;
+            ((1..=12).contains(&m) && (1..=5).contains(&w) && d <= 6)
+                .then_some(Date::Month(m, w, d))?
+        } else if self.eat('J') {
+            Date::Julian(self.number()?.clamp(1, 365))
+        } else {
+            Date::Zero(self.number()?.min(365))
+        };
+        let time = if self.eat('/') { self.offset()? } else { 7200 };
+        Some((date, time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seconds since the epoch of a UTC date and time.
+    fn at(y: i64, mo: i64, d: i64, h: i64, mi: i64, s: i64) -> i64 {
+        days_from_civil(y, mo, d) * 86_400 + h * 3600 + mi * 60 + s
+    }
+
+    fn rule(s: &str) -> Rule {
+        Rule::parse(s).unwrap_or_else(|| panic!("`{s}` should parse"))
+    }
+
+    /// A version 2 TZif file: an empty version 1 block, then `offsets`
+    /// (with made-up abbreviations), the `transitions` as
+    /// `(instant, offset index)` and the footer rule.
+    fn tzif(offsets: &[i32], transitions: &[(i64, u8)], footer: &str) -> Vec<u8> {
+        let header = |timecnt: usize, typecnt: usize, charcnt: usize| {
+            let mut h = b"TZif2".to_vec();
+            h.extend([0; 15]);
+            for count in [0, 0, 0, timecnt, typecnt, charcnt] {
+                h.extend((count as u32).to_be_bytes());
+            }
+            h
+        };
+        let mut data = header(0, 1, 4);
+        data.extend([0, 0, 0, 0, 0, 0]);
+        data.extend(b"UTC\0");
+        data.extend(header(transitions.len(), offsets.len(), 4 * offsets.len()));
+        for (t, _) in transitions {
+            data.extend(t.to_be_bytes());
+        }
+        data.extend(transitions.iter().map(|&(_, kind)| kind));
+        for (i, offset) in offsets.iter().enumerate() {
+            data.extend(offset.to_be_bytes());
+            data.extend([0, 4 * i as u8]);
+        }
+        for _ in offsets {
+            data.extend(b"ABC\0");
+        }
+        data.extend(format!("\n{footer}\n").bytes());
+        data
+    }
+
+    #[test]
+    fn tzif_transitions_then_footer_rule() {
+        let data = tzif(
+            &[-18_000, 3600],
+            &[(0, 1), (1000, 0)],
+            "CET-1CEST,M3.5.0,M10.5.0/3",
+        );
+        let zone = Zone::from_tzif(&data).unwrap();
+        assert_eq!(zone.offset_at(-1), -18_000);
+        assert_eq!(zone.offset_at(0), 3600);
+        assert_eq!(zone.offset_at(999), 3600);
+        // Past the last transition the footer rule takes over.
+        assert_eq!(zone.offset_at(1000), 3600);
+        assert_eq!(zone.offset_at(at(2025, 7, 1, 0, 0, 0)), 7200);
+    }
+
+    #[test]
+    fn tzif_rejects_garbage() {
+        assert!(Zone::from_tzif(b"").is_none());
+        assert!(Zone::from_tzif(b"TZif2").is_none());
+        let data = tzif(&[0], &[(0, 0)], "UTC0");
+        assert!(Zone::from_tzif(&data[..data.len() - 20]).is_none());
+        // A transition to an offset that does not exist.
+        assert!(Zone::from_tzif(&tzif(&[0], &[(0, 3)], "UTC0")).is_none());
+    }
+
+    #[test]
+    fn us_default_rules() {
+        let r = rule("EST5EDT");
+        assert_eq!(r.offset_at(at(2025, 1, 15, 12, 0, 0)), -18_000);
+        // Second Sunday of March, 02:00 EST.
+        assert_eq!(r.offset_at(at(2025, 3, 9, 6, 59, 59)), -18_000);
+        assert_eq!(r.offset_at(at(2025, 3, 9, 7, 0, 0)), -14_400);
+        // First Sunday of November, 02:00 EDT.
+        assert_eq!(r.offset_at(at(2025, 11, 2, 5, 59, 59)), -14_400);
+        assert_eq!(r.offset_at(at(2025, 11, 2, 6, 0, 0)), -18_000);
+    }
+
+    #[test]
+    fn central_european_rules() {
+        let r = rule("CET-1CEST,M3.5.0,M10.5.0/3");
+        assert_eq!(r.offset_at(at(2025, 3, 30, 0, 59, 59)), 3600);
+        assert_eq!(r.offset_at(at(2025, 3, 30, 1, 0, 0)), 7200);
+        assert_eq!(r.offset_at(at(2025, 10, 26, 0, 59, 59)), 7200);
+        assert_eq!(r.offset_at(at(2025, 10, 26, 1, 0, 0)), 3600);
+        // Far-off instants must not overflow.
+        for secs in [i64::MAX, i64::MIN] {
+            assert!([3600, 7200].contains(&r.offset_at(secs)));
+        }
+    }
+
+    #[test]
+    fn fixed_and_quoted_rules() {
+        assert_eq!(rule("UTC0").offset_at(0), 0);
+        assert_eq!(rule("<+0530>-5:30").offset_at(0), 19_800);
+        assert_eq!(rule("IST-5:30").offset_at(0), 19_800);
+        // Southern hemisphere: daylight saving time across the new year.
+        let r = rule("AEST-10AEDT,M10.1.0,M4.1.0/3");
+        assert_eq!(r.offset_at(at(2025, 1, 1, 0, 0, 0)), 39_600);
+        assert_eq!(r.offset_at(at(2025, 7, 1, 0, 0, 0)), 36_000);
+    }
+
+    #[test]
+    fn malformed_rules() {
+        for s in [
+            "",
+            "E5",
+            "EST",
+            "EST5EDT,M3.2.0",
+            "EST5EDT,M13.1.0,M11.1.0",
+            "EST5EDT,M3.6.0,M11.1.0",
+            "EST5EDT,M3.2.7,M11.1.0",
+            "EST5EDT,M3.2.0,M11.1.0x",
+            "<EST5",
+            "EST99999999999999999999",
+            "EST5000000000000000",
+            "EST5:5000000000000000000",
+            "EST5:00:9223372036854775807",
+            "EST5EDT,M3.2.0/5000000000000000,M11.1.0",
+        ] {
+            assert!(Rule::parse(s).is_none(), "`{s}` should not parse");
+        }
+    }
+}
@@ -0,0 +1,743 @@
+// ---------- interactive viewer ----------
+//
+// State behind `depthlog view`: a cursor over the records of a `CallTree`,
+// a fold flag per invocation, search and column toggles. Folding an
+// invocation hides everything after its first record up to the end of its
+// subtree; the remaining line says how many records it hides.
+//
+// The cursor can also be moved like a debugger, by the `depth` of the
+// records: step into (the next record), over (the next one at the same or a
+// lower depth) and out (the next one at a lower depth), and back. The top
+// line shows the call stack of the record under the cursor.
+//
+// `Viewer` knows nothing about the terminal beyond `Key`: it takes key
+// presses and draws whole frames of ANSI text for the caller to write.
+
+use crate::calltree::{CallTree, NodeId};
+use crate::color::{BOLD, DIM, RESET, truncate_visible, visible_width};
+use crate::guides::GuideStyle;
+use crate::record::Record;
+use crate::render::{Multiline, RenderConfig, Renderer};
+use crate::terminal::Key;
+
+const REVERSE: &str = "\x1b[7m";
+
+const HELP: &[(&str, &str)] = &[
+    ("j k Down Up", "next / previous line"),
+    ("space PgDn PgUp", "next / previous page"),
+    ("g G Home End", "first / last line"),
+    ("Enter Tab", "fold / unfold the call under the cursor"),
+    ("h Left", "fold, or go to the parent call when folded"),
+    ("l Right", "unfold"),
+    ("- +", "fold / unfold everything"),
+    ("s S", "step into: next / previous record"),
+    (
+        "o O",
+        "step over: next / previous record at the same or a lower depth",
+    ),
+    ("u U", "step out: next / previous record at a lower depth"),
+    ("p", "parent call"),
+    ("] [", "next / previous sibling call"),
+    ("/", "search func, file, msg and extra fields"),
+    ("n N", "next / previous match"),
+    ("1 2 3 4", "toggle time, level, file:line, extra fields"),
+    ("?", "this help"),
+    ("q Ctrl-C", "quit"),
+];
+
+/// Cursor, folds and display settings over a loaded call tree.
+pub struct Viewer {
+    tree: CallTree,
+    config: RenderConfig,
+    /// Indexed by `NodeId`.
+    folded: Vec<bool>,
+    /// Record under the cursor; always a visible one.
+    cursor: usize,
+    /// First record on screen.
+    top: usize,
+    /// Rows available for records.
+    height: usize,
+    /// Shown on the status line, e.g. the file name.
+    name: String,
+    /// The search being typed after `/`.
+    prompt: Option<String>,
+    /// The last search.
+    query: Option<String>,
+    /// Shown on the status line until the next key.
+    message: Option<String>,
+    help: bool,
+}
+
+impl Viewer {
+    /// Records are drawn with `config`, except that messages are always
+    /// collapsed to one line and guides, durations are off.
+    pub fn new(tree: CallTree, mut config: RenderConfig, name: &str) -> Self {
+        config.multiline = Multiline::Collapse;
+        config.guides = GuideStyle::Spaces;
+        config.durations = false;
+        Viewer {
+            folded: vec![false; tree.nodes.len()],
+            tree,
+            config,
+            cursor: 0,
+            top: 0,
+            height: 1,
+            name: name.to_string(),
+            prompt: None,
+            query: None,
+            message: None,
+            help: false,
+        }
+    }
+
+    pub fn tree(&self) -> &CallTree {
+        &self.tree
+    }
+
+    /// Index of the record under the cursor.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    // ---------- visibility ----------
+
+    /// The outermost folded invocation that hides record `r`, if any. The
+    /// first record of a folded invocation stays visible.
+    fn hidden_by(&self, r: usize) -> Option<NodeId> {
+        let mut owner = None;
+        let mut id = Some(self.tree.node_of[r]);
+        while let Some(n) = id {
+            let node = self.tree.node(n);
+            if self.folded[n.0] && node.span.start != r {
+                owner = Some(n);
+            }
+            id = node.parent;
+        }
+        owner
+    }
+
+    /// The visible line that stands for record `r`.
+    fn visible(&self, r: usize) -> usize {
+        self.hidden_by(r)
+            .map_or(r, |n| self.tree.node(n).span.start)
+    }
+
+    fn next_row(&self, r: usize) -> Option<usize> {
+        let mut next = r + 1;
+        while next < self.tree.records.len() {
+            match self.hidden_by(next) {
+                Some(n) => next = self.tree.node(n).span.end,
+                None => return Some(next),
+            }
+        }
+        None
+    }
+
+    fn prev_row(&self, r: usize) -> Option<usize> {
+        r.checked_sub(1).map(|p| self.visible(p))
+    }
+
+    /// Unfold whatever hides record `r`.
+    fn reveal(&mut self, r: usize) {
+        while let Some(n) = self.hidden_by(r) {
+            self.folded[n.0] = false;
+        }
+    }
+
+    // ---------- movement ----------
+
+    fn down(&mut self, n: usize) {
+        for _ in 0..n {
+            match self.next_row(self.cursor) {
+                Some(r) => self.cursor = r,
+                None => break,
+            }
+        }
+    }
+
+    fn up(&mut self, n: usize) {
+        for _ in 0..n {
+            match self.prev_row(self.cursor) {
+                Some(r) => self.cursor = r,
+                None => break,
+            }
+        }
+    }
+
+    /// Move to the first record of `id`.
+    fn goto_node(&mut self, id: NodeId) {
+        let r = self.tree.node(id).span.start;
+        self.reveal(r);
+        self.cursor = r;
+    }
+
+    fn parent(&mut self) {
+        match self.tree.node(self.tree.node_of[self.cursor]).parent {
+            Some(p) => self.goto_node(p),
+            None => self.message = Some("already at the top level".to_string()),
+        }
+    }
+
+    fn sibling(&mut self, forward: bool) {
+        let id = self.tree.node_of[self.cursor];
+        let siblings = match self.tree.node(id).parent {
+            Some(p) => &self.tree.node(p).children,
+            None => &self.tree.roots,
+        };
+        let pos = siblings.iter().position(|&s| s == id).unwrap_or(0);
+        let target = if forward {
+            siblings.get(pos + 1)
+        } else {
+            pos.checked_sub(1).and_then(|p| siblings.get(p))
+        };
+        match target.copied() {
+            Some(s) => self.goto_node(s),
+            None if forward => self.message = Some("no next sibling".to_string()),
+            None => self.message = Some("no previous sibling".to_string()),
+        }
+    }
+
+    /// Move to the next (or previous) record whose depth is at most
+    /// `max_depth`, unfolding it if needed.
+    fn step(&mut self, forward: bool, max_depth: usize) {
+        let records = &self.tree.records;
+        let found = if forward {
+            (self.cursor + 1..records.len()).find(|&r| records[r].depth <= max_depth)
+        } else {
+            (0..self.cursor)
+                .rev()
+                .find(|&r| records[r].depth <= max_depth)
+        };
+        match found {
+            Some(r) => {
+                self.reveal(r);
+                self.cursor = r;
+            }
+            None if forward => self.message = Some("end of trace".to_string()),
+            None => self.message = Some("start of trace".to_string()),
+        }
+    }
+
+    fn step_out(&mut self, forward: bool) {
+        match self.tree.records[self.cursor].depth.checked_sub(1) {
+            Some(depth) => self.step(forward, depth),
+            None => self.message = Some("already at the top level".to_string()),
+        }
+    }
+
+    // ---------- folding ----------
+
+    fn foldable(&self, id: NodeId) -> bool {
+        self.tree.node(id).span.len() > 1
+    }
+
+    fn set_fold(&mut self, id: NodeId, folded: bool) {
+        if !self.foldable(id) {
+            self.message = Some("nothing to fold".to_string());
+            return;
+        }
+        self.folded[id.0] = folded;
+        self.cursor = self.visible(self.cursor);
+    }
+
+    fn fold_all(&mut self, folded: bool) {
+        for i in 0..self.folded.len() {
+            self.folded[i] = folded && self.foldable(NodeId(i));
+        }
+        self.cursor = self.visible(self.cursor);
+    }
+
+    // ---------- search ----------
+
+    fn search(&mut self, forward: bool) {
+        let Some(query) = self.query.clone() else {
+            self.message = Some("no previous search".to_string());
+            return;
+        };
+        let len = self.tree.records.len();
+        for step in 1..=len {
+            let r = if forward {
+                (self.cursor + step) % len
+            } else {
+                (self.cursor + len - step) % len
+            };
+            if matches(&self.tree.records[r], &query) {
+                let wrapped = if forward {
+                    r <= self.cursor
+                } else {
+                    r >= self.cursor
+                };
+                if wrapped {
+                    self.message = Some("search wrapped".to_string());
+                }
+                self.reveal(r);
+                self.cursor = r;
+                return;
+            }
+        }
+        self.message = Some(format!("not found: {query}"));
+    }
+
+    // ---------- input ----------
+
+    /// Act on one key press; returns `false` when the viewer should close.
+    pub fn handle_key(&mut self, key: Key) -> bool {
+        self.message = None;
+        if self.tree.records.is_empty() {
+            return !matches!(key, Key::Char('q') | Key::Ctrl('c'));
+        }
+        if let Some(prompt) = &mut self.prompt {
+            match key {
+                Key::Char(c) => prompt.push(c),
+                Key::Backspace if !prompt.is_empty() => {
+                    prompt.pop();
+                }
+                Key::Enter => {
+                    if !prompt.is_empty() {
+                        self.query = self.prompt.take();
+                    }
+                    self.prompt = None;
+                    self.search(true);
+                }
+                _ => self.prompt = None,
+            }
+            return true;
+        }
+        if self.help {
+            self.help = false;
+            return !matches!(key, Key::Char('q') | Key::Ctrl('c'));
+        }
+
+        let node = self.tree.node_of[self.cursor];
+        let depth = self.tree.records[self.cursor].depth;
+        let page = self.height.saturating_sub(1).max(1);
+        match key {
+            Key::Char('q') | Key::Ctrl('c') => return false,
+            Key::Char('j') | Key::Down | Key::Ctrl('n') => self.down(1),
+            Key::Char('k') | Key::Up | Key::Ctrl('p') => self.up(1),
+            Key::Char(' ') | Key::PageDown | Key::Ctrl('f') => self.down(page),
+            Key::PageUp | Key::Ctrl('b') => self.up(page),
+            Key::Char('g') | Key::Home => self.cursor = 0,
+            Key::Char('G') | Key::End => self.cursor = self.visible(self.tree.records.len() - 1),
+            Key::Enter | Key::Tab => self.set_fold(node, !self.folded[node.0]),
+            Key::Char('h') | Key::Left => {
+                if self.foldable(node) && !self.folded[node.0] {
+                    self.set_fold(node, true);
+                } else {
+                    self.parent();
+                }
+            }
+            Key::Char('l') | Key::Right => self.set_fold(node, false),
+            Key::Char('-') => self.fold_all(true),
+            Key::Char('+' | '=') => self.fold_all(false),
+            Key::Char('s') => self.step(true, usize::MAX),
+            Key::Char('S') => self.step(false, usize::MAX),
+            Key::Char('o') => self.step(true, depth),
+            Key::Char('O') => self.step(false, depth),
+            Key::Char('u') => self.step_out(true),
+            Key::Char('U') => self.step_out(false),
+            Key::Char('p') => self.parent(),
+            Key::Char(']') => self.sibling(true),
+            Key::Char('[') => self.sibling(false),
+            Key::Char('/') => self.prompt = Some(String::new()),
+            Key::Char('n') => self.search(true),
+            Key::Char('N') => self.search(false),
+            Key::Char('1') => self.config.show_time ^= true,
+            Key::Char('2') => self.config.show_level ^= true,
+            Key::Char('3') => self.config.show_location ^= true,
+            Key::Char('4') => self.config.show_extra ^= true,
+            Key::Char('?') => self.help = true,
+            _ => {}
+        }
+        true
+    }
+
+    // ---------- drawing ----------
+
+    /// Keep the cursor on screen.
+    fn scroll(&mut self) {
+        self.top = self.visible(self.top.min(self.cursor));
+        let mut r = self.top;
+        let mut rows = 0;
+        while r < self.cursor && rows < self.height {
+            match self.next_row(r) {
+                Some(next) => r = next,
+                None => break,
+            }
+            rows += 1;
+        }
+        if rows >= self.height {
+            self.top = self.cursor;
+            for _ in 1..self.height {
+                match self.prev_row(self.top) {
+                    Some(p) => self.top = p,
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// A full frame for a `rows` x `cols` terminal: the call stack on top,
+    /// records, then a status line at the bottom.
+    pub fn draw(&mut self, rows: usize, cols: usize) -> String {
+        self.height = rows.saturating_sub(2).max(1);
+        let mut frame = format!("\x1b[1;1H\x1b[2K{}", self.breadcrumb(cols));
+        let last = self.height + 1;
+        let mut y = 2;
+        if self.help {
+            for (keys, what) in HELP.iter().take(self.height) {
+                let line = format!("  {keys:<18}{what}");
+                frame.push_str(&format!(
+                    "\x1b[{y};1H\x1b[2K{}",
+                    truncate_visible(&line, cols)
+                ));
+                y += 1;
+            }
+        } else if !self.tree.records.is_empty() {
+            self.scroll();
+            let mut renderer = Renderer::new(self.config.clone());
+            let mut r = Some(self.top);
+            while let Some(idx) = r
+                && y <= last
+            {
+                let line = self.line(&mut renderer, idx, cols);
+                frame.push_str(&format!("\x1b[{y};1H\x1b[2K{line}"));
+                y += 1;
+                r = self.next_row(idx);
+            }
+        }
+        while y <= last {
+            frame.push_str(&format!("\x1b[{y};1H\x1b[2K"));
+            y += 1;
+        }
+
+        frame.push_str(&format!("\x1b[{};1H\x1b[2K", last + 1));
+        match &self.prompt {
+            Some(prompt) => {
+                frame.push_str(&truncate_visible(&format!("/{prompt}"), cols));
+                frame.push_str("\x1b[?25h");
+            }
+            None => {
+                frame.push_str(&self.status(cols));
+                frame.push_str("\x1b[?25l");
+            }
+        }
+        frame
+    }
+
+    /// Record `r` as one screen line: fold marker, the pretty line, and what
+    /// a fold hides.
+    fn line(&self, renderer: &mut Renderer, r: usize, cols: usize) -> String {
+        let rec = &self.tree.records[r];
+        let id = self.tree.node_of[r];
+        let node = self.tree.node(id);
+        let head = node.span.start == r && self.foldable(id);
+
+        let mut line = String::from(match (head, self.folded[id.0]) {
+            (true, true) => "▸ ",
+            (true, false) => "▾ ",
+            _ => "  ",
+        });
+        line.push_str(renderer.render_to_string(rec).trim_end_matches('\n'));
+        if head && self.folded[id.0] {
+            let hidden = node.span.len() - 1;
+            if self.config.color {
+                line.push_str(&format!(" {DIM}… {hidden} more{RESET}"));
+            } else {
+                line.push_str(&format!(" … {hidden} more"));
+            }
+        }
+
+        let mut line = truncate_visible(&line, cols);
+        if r == self.cursor {
+            let pad = cols.saturating_sub(visible_width(&line));
+            line.extend(std::iter::repeat_n(' ', pad));
+            line = format!(
+                "{REVERSE}{}{RESET}",
+                line.replace(RESET, &format!("{RESET}{REVERSE}"))
+            );
+        }
+        line
+    }
+
+    /// The invocations leading to the record under the cursor, outermost
+    /// first: `main › xbtree_insert › lock_object`. When too long, the
+    /// outermost calls give way.
+    fn breadcrumb(&self, cols: usize) -> String {
+        if self.tree.records.is_empty() {
+            return String::new();
+        }
+        let names: Vec<&str> = self
+            .tree
+            .ancestry(self.tree.node_of[self.cursor])
+            .into_iter()
+            .map(|id| self.tree.node(id).func.as_deref().unwrap_or("?"))
+            .collect();
+        let mut skip = 0;
+        let mut line = names.join(" › ");
+        while line.chars().count() > cols && skip + 1 < names.len() {
+            skip += 1;
+            line = format!("… › {}", names[skip..].join(" › "));
+        }
+        let line = truncate_visible(&line, cols);
+        if self.config.color {
+            format!("{BOLD}{line}{RESET}")
+        } else {
+            line
+        }
+    }
+
+    fn status(&self, cols: usize) -> String {
+        let left = match &self.message {
+            Some(msg) => msg.clone(),
+            None => {
+                let rec = &self.tree.records[self.cursor];
+                format!(
+                    "{}  {}/{}  depth {}  {}",
+                    self.name,
+                    self.cursor + 1,
+                    self.tree.records.len(),
+                    rec.depth,
+                    rec.func.as_deref().unwrap_or("?"),
+                )
+            }
+        };
+        let left = if self.tree.records.is_empty() {
+            format!("{}  no records", self.name)
+        } else {
+            left
+        };
+        let right = "? help ";
+        let gap = cols.saturating_sub(visible_width(&left) + right.len());
+        let line = format!("{left}{}{right}", " ".repeat(gap));
+        format!("{REVERSE}{}{RESET}", truncate_visible(&line, cols))
+    }
+}
+
+/// Whether `query` occurs in the function, file, message or an extra field
+/// (key or value) of `rec`.
+fn matches(rec: &Record, query: &str) -> bool {
+    [&rec.func, &rec.file, &rec.msg]
+        .into_iter()
+        .flatten()
+        .any(|s| s.contains(query))
+        || rec
+            .extra
+            .iter()
+            .any(|(k, v)| k.contains(query) || v.contains(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// main
+    ///   a        (records 1, 4)
+    ///     b      (records 2, 3)
+    ///   c
+    ///     d
+    /// main
+    fn viewer() -> Viewer {
+        let lines = [
+            "depth=0 func=main msg=start",
+            "depth=1 func=a msg=enter",
+            "depth=2 func=b msg=lock",
+            "depth=2 func=b msg=unlock",
+            "depth=1 func=a msg=leave",
+            "depth=1 func=c msg=flush",
+            "depth=2 func=d msg=write",
+            "depth=0 func=main msg=end",
+        ];
+        let tree = CallTree::build(lines.iter().map(|l| Record::parse(l).unwrap()));
+        let config = RenderConfig {
+            show_time: false,
+            show_location: false,
+            ..RenderConfig::default()
+        };
+        Viewer::new(tree, config, "test.log")
+    }
+
+    /// Press `keys` in order; returns the cursor after each.
+    fn press(v: &mut Viewer, keys: &[Key]) -> Vec<usize> {
+        keys.iter()
+            .map(|&key| {
+                assert!(v.handle_key(key));
+                v.cursor()
+            })
+            .collect()
+    }
+
+    /// The rows of a frame without escape sequences, reversed ones marked
+    /// with `>`.
+    fn rows(frame: &str) -> Vec<String> {
+        frame
+            .split("\x1b[2K")
+            .skip(1)
+            .map(|row| {
+                let mut text = String::new();
+                let mut chars = row.chars();
+                while let Some(c) = chars.next() {
+                    if c == '\x1b' {
+                        chars.by_ref().find(|c| c.is_ascii_alphabetic());
+                    } else {
+                        text.push(c);
+                    }
+                }
+                let mark = if row.contains(REVERSE) { "> " } else { "" };
+                format!("{mark}{}", text.trim_end())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn moving_over_lines() {
+        let mut v = viewer();
+        assert_eq!(
+            press(
+                &mut v,
+                &[Key::Down, Key::Char('j'), Key::Up, Key::End, Key::Home]
+            ),
+            [1, 2, 1, 7, 0]
+        );
+        assert_eq!(press(&mut v, &[Key::Up]), [0]);
+        v.draw(5, 40);
+        // Three rows for records: a page moves two.
+        assert_eq!(
+            press(&mut v, &[Key::PageDown, Key::PageDown, Key::PageUp]),
+            [2, 4, 2]
+        );
+    }
+
+    #[test]
+    fn folding_hides_the_rest_of_a_call() {
+        let mut v = viewer();
+        press(&mut v, &[Key::Down, Key::Enter]);
+        assert!(v.folded[1]);
+        // Past the folded `a` straight to `c`, and back.
+        assert_eq!(press(&mut v, &[Key::Down, Key::Up]), [5, 1]);
+        assert_eq!(press(&mut v, &[Key::Tab, Key::Down]), [1, 2]);
+        // `h` folds, then goes to the parent once folded.
+        assert_eq!(press(&mut v, &[Key::Up, Key::Left]), [1, 1]);
+        assert!(v.folded[1]);
+        assert_eq!(press(&mut v, &[Key::Char('h')]), [0]);
+        press(&mut v, &[Key::Char('j'), Key::Right]);
+        assert!(!v.folded[1]);
+    }
+
+    #[test]
+    fn folding_everything_moves_the_cursor_to_a_visible_line() {
+        let mut v = viewer();
+        press(&mut v, &[Key::Char('G'), Key::Up]);
+        assert_eq!(v.cursor(), 6);
+        assert_eq!(press(&mut v, &[Key::Char('-')]), [0]);
+        assert_eq!(press(&mut v, &[Key::End]), [0]);
+        assert_eq!(press(&mut v, &[Key::Char('+'), Key::End]), [0, 7]);
+        // A call of one record cannot fold.
+        press(&mut v, &[Key::Up, Key::Enter]);
+        assert!(!v.folded[4]);
+        assert_eq!(v.message.as_deref(), Some("nothing to fold"));
+    }
+
+    #[test]
+    fn parent_and_sibling_calls() {
+        let mut v = viewer();
+        press(&mut v, &[Key::Down]);
+        assert_eq!(press(&mut v, &[Key::Char(']'), Key::Char(']')]), [5, 5]);
+        assert_eq!(v.message.as_deref(), Some("no next sibling"));
+        assert_eq!(press(&mut v, &[Key::Char('['), Key::Char('p')]), [1, 0]);
+        assert_eq!(press(&mut v, &[Key::Char('p')]), [0]);
+        assert_eq!(v.message.as_deref(), Some("already at the top level"));
+        // From a nested call up through its parents.
+        press(&mut v, &[Key::End, Key::Up]);
+        assert_eq!(press(&mut v, &[Key::Char('p'), Key::Char('p')]), [5, 0]);
+    }
+
+    /// Type `/query` and Enter; returns the cursor.
+    fn type_query(v: &mut Viewer, query: &str) -> Option<usize> {
+        press(v, &[Key::Char('/')]);
+        for c in query.chars() {
+            press(v, &[Key::Char(c)]);
+        }
+        press(v, &[Key::Enter]).pop()
+    }
+
+    #[test]
+    fn search_moves_to_matches_and_wraps() {
+        let mut v = viewer();
+        assert_eq!(type_query(&mut v, "lock"), Some(2));
+        assert_eq!(press(&mut v, &[Key::Char('n')]), [3]);
+        assert_eq!(press(&mut v, &[Key::Char('n')]), [2]);
+        assert_eq!(v.message.as_deref(), Some("search wrapped"));
+        assert_eq!(press(&mut v, &[Key::Char('N')]), [3]);
+        assert_eq!(type_query(&mut v, "nope"), Some(3));
+        assert_eq!(v.message.as_deref(), Some("not found: nope"));
+        // A match inside a fold unfolds it.
+        press(&mut v, &[Key::Home, Key::Char('-')]);
+        assert_eq!(type_query(&mut v, "write"), Some(6));
+        assert_eq!(v.hidden_by(6), None);
+    }
+
+    #[test]
+    fn draws_a_frame() {
+        let mut v = viewer();
+        press(&mut v, &[Key::Down, Key::Enter, Key::Char('4')]);
+        let frame = v.draw(6, 40);
+        assert_eq!(
+            rows(&frame),
+            [
+                "main › a",
+                "▾ [?] | main: start",
+                "> ▸ [?] |     a: enter … 3 more",
+                "▾ [?] |     c: flush",
+                "  [?] |         d: write",
+                "> test.log  2/8  depth 1  a        ? help",
+            ]
+        );
+    }
+
+    #[test]
+    fn stepping_by_depth() {
+        let mut v = viewer();
+        // Into: every record.
+        assert_eq!(press(&mut v, &[Key::Char('s'), Key::Char('s')]), [1, 2]);
+        // Over: the next record at the same or a lower depth.
+        assert_eq!(press(&mut v, &[Key::Char('o'), Key::Char('o')]), [3, 4]);
+        assert_eq!(press(&mut v, &[Key::Char('o'), Key::Char('o')]), [5, 7]);
+        // Back over from `main: end` skips everything deeper.
+        assert_eq!(press(&mut v, &[Key::Char('O'), Key::Char('S')]), [0, 0]);
+        assert_eq!(v.message.as_deref(), Some("start of trace"));
+        press(&mut v, &[Key::End]);
+        assert_eq!(press(&mut v, &[Key::Char('s')]), [7]);
+        assert_eq!(v.message.as_deref(), Some("end of trace"));
+    }
+
+    #[test]
+    fn stepping_out_and_back() {
+        let mut v = viewer();
+        press(&mut v, &[Key::Down, Key::Down]);
+        // Out of `b` to the rest of `a`, then out of `a` to `main`.
+        assert_eq!(press(&mut v, &[Key::Char('u'), Key::Char('u')]), [4, 7]);
+        assert_eq!(press(&mut v, &[Key::Char('u')]), [7]);
+        assert_eq!(v.message.as_deref(), Some("already at the top level"));
+        press(&mut v, &[Key::End, Key::Up]);
+        assert_eq!(press(&mut v, &[Key::Char('U'), Key::Char('U')]), [5, 0]);
+    }
+
+    #[test]
+    fn stepping_into_a_fold_opens_it() {
+        let mut v = viewer();
+        press(&mut v, &[Key::Char('-')]);
+        assert_eq!(press(&mut v, &[Key::Char('s'), Key::Char('s')]), [1, 2]);
+        assert_eq!(v.hidden_by(2), None);
+        assert!(v.folded[3]);
+    }
+
+    #[test]
+    fn breadcrumb_shows_the_call_stack() {
+        let mut v = viewer();
+        press(&mut v, &[Key::End, Key::Up]);
+        assert_eq!(v.breadcrumb(40), "main › c › d");
+        // Outermost calls give way first when it does not fit.
+        assert_eq!(v.breadcrumb(10), "… › c › d");
+    }
+}